@@ -1 +1 @@
-'((parameters . ((seed u32))))
+'()
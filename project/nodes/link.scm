@@ -1,2 +0,0 @@
-'((parameters .
-    ((start-idx u16) (end-idx u16) (style u8))))
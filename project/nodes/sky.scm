@@ -1,3 +1 @@
-'((parameters .
-   ((stars (vec (node star)))
-    (links (vec (node link))))))
+'((parameters . ((seed u32))))
@@ -1,2 +0,0 @@
-'((parameters .
-    ((x i16) (y i16) (bright bool))))
@@ -1,3 +1,3 @@
 '((name . AOC)
-  (root-node . (interactive-sky 123456))
+  (root-node . (app))
   (tile-types . (dummy)))
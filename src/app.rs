@@ -0,0 +1,355 @@
+#[skylite_proc::node_definition("./project/project.scm", "app")]
+mod app {
+    use skylite_core::{ProjectControls, RenderControls};
+    use wasm4_target::{
+        BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, GAMEPAD1,
+        MOUSE_BUTTONS, MOUSE_LEFT, MOUSE_RIGHT, MOUSE_X, MOUSE_Y,
+    };
+
+    use crate::Aoc;
+    use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+    use crate::framebuffer::{SCREEN_SIZE, View, Wasm4Framebuffer};
+    use crate::interactive_sky::InteractiveSky;
+    use crate::render::{
+        MENU_LINE_HEIGHT, MINIMAP_LEFT, MINIMAP_SIZE, MINIMAP_TOP, PAUSE_BUTTON_SIZE,
+        SEED_DIGITS_TOP, focus_from_overview, menu_top, overview_scale, render_menu,
+        render_minimap, render_overview, render_pause_button, render_seed_selection,
+        render_transition, seed_digits_left,
+    };
+    use crate::scene::{
+        Menu, PauseItem, SEED_DIGITS, Scene, SeedSelection, TRANSITION_FRAMES, TitleItem,
+        pause_label, random_seed, title_label,
+    };
+    use crate::util::next_random;
+
+    /// Button presses of a single frame, from both the mouse and the gamepad.
+    struct Pressed {
+        mouse: u8,
+        gamepad: u8,
+        /// Gamepad buttons that are held down, including the pressed ones.
+        gamepad_held: u8,
+        mouse_pos: (i16, i16),
+        mouse_moved: bool,
+    }
+
+    /// The root node, which switches between the scenes of the game.
+    pub(crate) struct App {
+        scene: Scene,
+        title_menu: Menu<TitleItem>,
+        pause_menu: Menu<PauseItem>,
+        seed_selection: SeedSelection,
+        /// The spot that the overview is centered on when it is closed, in screen pixels.
+        overview_pos: (i32, i32),
+        /// Frames left until the fade into the current scene is complete.
+        transition: u32,
+        /// Frames since the start, used to pick random seeds.
+        frame: u32,
+        prev_mouse_buttons: u8,
+        prev_mouse_pos: (i16, i16),
+        prev_gamepad: u8,
+        #[skylite_proc::node]
+        sky: InteractiveSky,
+    }
+
+    fn title_menu(resumable: bool) -> Menu<TitleItem> {
+        if resumable {
+            Menu::new(vec![TitleItem::Continue, TitleItem::NewSky])
+        } else {
+            Menu::new(vec![TitleItem::NewSky])
+        }
+    }
+
+    impl App {
+        #[skylite_proc::new]
+        pub(crate) fn new() -> App {
+            let sky = InteractiveSky::new();
+            App {
+                scene: Scene::Title,
+                title_menu: title_menu(sky.resumable),
+                pause_menu: Menu::new(PauseItem::all()),
+                seed_selection: SeedSelection::new(0),
+                overview_pos: (0, 0),
+                transition: TRANSITION_FRAMES,
+                frame: 0,
+                prev_mouse_buttons: 0,
+                prev_mouse_pos: (0, 0),
+                prev_gamepad: 0,
+                sky,
+            }
+        }
+
+        fn read_pressed(&mut self) -> Pressed {
+            let mouse_buttons = unsafe { *MOUSE_BUTTONS };
+            let mouse_pos = unsafe { (*MOUSE_X, *MOUSE_Y) };
+            let gamepad = unsafe { *GAMEPAD1 };
+            let pressed = Pressed {
+                mouse: mouse_buttons & !self.prev_mouse_buttons,
+                gamepad: gamepad & !self.prev_gamepad,
+                gamepad_held: gamepad,
+                mouse_pos,
+                mouse_moved: mouse_pos != self.prev_mouse_pos,
+            };
+            self.prev_mouse_buttons = mouse_buttons;
+            self.prev_mouse_pos = mouse_pos;
+            self.prev_gamepad = gamepad;
+            pressed
+        }
+
+        fn switch_to(&mut self, scene: Scene) {
+            self.scene = scene;
+            self.transition = TRANSITION_FRAMES;
+        }
+
+        fn start_seed_selection(&mut self, seed: u32) {
+            self.seed_selection = SeedSelection::new(seed);
+            self.switch_to(Scene::SeedSelection);
+        }
+
+        /// Update the selection of a menu from the input, and return
+        /// whether the selected entry was confirmed.
+        fn update_menu<T: Copy>(menu: &mut Menu<T>, pressed: &Pressed) -> bool {
+            if pressed.gamepad & BUTTON_UP != 0 {
+                menu.move_selection(-1);
+            }
+            if pressed.gamepad & BUTTON_DOWN != 0 {
+                menu.move_selection(1);
+            }
+
+            // Entries are selected by hovering over them with the mouse.
+            let offset = pressed.mouse_pos.1 as i32 - menu_top(menu.items.len()) + 2;
+            let hovered = offset.div_euclid(MENU_LINE_HEIGHT) as usize;
+            let on_entry = offset >= 0 && hovered < menu.items.len();
+            if on_entry && pressed.mouse_moved {
+                menu.selected = hovered;
+            }
+
+            pressed.gamepad & BUTTON_1 != 0 || (on_entry && pressed.mouse & MOUSE_LEFT != 0)
+        }
+
+        fn update_title(&mut self, pressed: &Pressed) {
+            if !Self::update_menu(&mut self.title_menu, pressed) {
+                return;
+            }
+            match self.title_menu.current() {
+                TitleItem::Continue => self.switch_to(Scene::Playing),
+                TitleItem::NewSky => self.start_seed_selection(random_seed(self.frame)),
+            }
+        }
+
+        fn update_seed_selection(&mut self, pressed: &Pressed) {
+            let selection = &mut self.seed_selection;
+            let directions = [
+                (BUTTON_LEFT, -1, 0),
+                (BUTTON_RIGHT, 1, 0),
+                (BUTTON_UP, 0, 1),
+                (BUTTON_DOWN, 0, -1),
+            ];
+            for (button, move_delta, change_delta) in directions {
+                if pressed.gamepad & button != 0 {
+                    selection.move_digit(move_delta);
+                    selection.change_digit(change_delta);
+                }
+            }
+
+            // Clicking a digit changes it, left click counts up and right click down.
+            let (mouse_x, mouse_y) = (pressed.mouse_pos.0 as i32, pressed.mouse_pos.1 as i32);
+            let advance = 2 * (GLYPH_WIDTH + 1);
+            let offset = mouse_x - seed_digits_left() + 1;
+            let digit = offset.div_euclid(advance) as usize;
+            let on_digit = offset >= 0
+                && digit < SEED_DIGITS
+                && (SEED_DIGITS_TOP - 3..SEED_DIGITS_TOP + GLYPH_HEIGHT + 3).contains(&mouse_y);
+            if on_digit && pressed.mouse & (MOUSE_LEFT | MOUSE_RIGHT) != 0 {
+                selection.digit = digit;
+                let delta = if pressed.mouse & MOUSE_LEFT != 0 {
+                    1
+                } else {
+                    -1
+                };
+                selection.change_digit(delta);
+                return;
+            }
+
+            if pressed.gamepad & BUTTON_2 != 0 || pressed.mouse & MOUSE_RIGHT != 0 {
+                self.switch_to(Scene::Title);
+            } else if pressed.gamepad & BUTTON_1 != 0 || pressed.mouse & MOUSE_LEFT != 0 {
+                self.sky.start(self.seed_selection.seed);
+                self.switch_to(Scene::Playing);
+            }
+        }
+
+        fn update_playing(&mut self, pressed: &Pressed, focus: (i32, i32)) {
+            let (mouse_x, mouse_y) = (pressed.mouse_pos.0 as i32, pressed.mouse_pos.1 as i32);
+            let on_pause_button =
+                mouse_x >= SCREEN_SIZE as i32 - PAUSE_BUTTON_SIZE && mouse_y < PAUSE_BUTTON_SIZE;
+            let on_minimap = (MINIMAP_LEFT..MINIMAP_LEFT + MINIMAP_SIZE).contains(&mouse_x)
+                && (MINIMAP_TOP..MINIMAP_TOP + MINIMAP_SIZE).contains(&mouse_y);
+            let clicked = pressed.mouse & MOUSE_LEFT != 0;
+            // Button 2 together with left and right is undo and redo, with up
+            // it pauses and with down it opens the overview.
+            let gamepad_combo =
+                |button| pressed.gamepad_held & BUTTON_2 != 0 && pressed.gamepad & button != 0;
+            if gamepad_combo(BUTTON_UP) || (on_pause_button && clicked) {
+                self.pause_menu.selected = 0;
+                self.switch_to(Scene::Paused);
+            } else if gamepad_combo(BUTTON_DOWN) || (on_minimap && clicked) {
+                let scale = overview_scale(&self.sky.game().params);
+                let half_screen = SCREEN_SIZE as i32 / 2;
+                self.overview_pos = (
+                    (focus.0 + half_screen) / scale,
+                    (focus.1 + half_screen) / scale,
+                );
+                self.switch_to(Scene::Overview);
+            } else if self.sky.is_over() {
+                self.switch_to(Scene::Results);
+            }
+        }
+
+        /// Move the marked spot with the mouse or the D-pad. Clicking or button 1
+        /// moves the camera there, the right mouse button or button 2 goes back.
+        fn update_overview(&mut self, pressed: &Pressed) {
+            const STEP: i32 = 2;
+
+            if pressed.mouse_moved {
+                self.overview_pos = (pressed.mouse_pos.0 as i32, pressed.mouse_pos.1 as i32);
+            }
+            for (button, dx, dy) in [
+                (BUTTON_LEFT, -STEP, 0),
+                (BUTTON_RIGHT, STEP, 0),
+                (BUTTON_UP, 0, -STEP),
+                (BUTTON_DOWN, 0, STEP),
+            ] {
+                if pressed.gamepad_held & button != 0 {
+                    self.overview_pos.0 += dx;
+                    self.overview_pos.1 += dy;
+                }
+            }
+            let max = SCREEN_SIZE as i32 - 1;
+            self.overview_pos = (
+                self.overview_pos.0.clamp(0, max),
+                self.overview_pos.1.clamp(0, max),
+            );
+
+            if pressed.gamepad & BUTTON_2 != 0 || pressed.mouse & MOUSE_RIGHT != 0 {
+                self.switch_to(Scene::Playing);
+            } else if pressed.gamepad & BUTTON_1 != 0 || pressed.mouse & MOUSE_LEFT != 0 {
+                let (x, y) = self.overview_pos;
+                let (focus_x, focus_y) = focus_from_overview(&self.sky.game().params, x, y);
+                self.sky.ease_camera_to(focus_x, focus_y);
+                // Also move the pointer, so that a camera following it stays there.
+                let half_screen = SCREEN_SIZE as i32 / 2;
+                self.sky
+                    .move_pointer_to(focus_x + half_screen, focus_y + half_screen);
+                self.switch_to(Scene::Playing);
+            }
+        }
+
+        fn update_paused(&mut self, pressed: &Pressed) {
+            if pressed.gamepad & BUTTON_2 != 0 || pressed.mouse & MOUSE_RIGHT != 0 {
+                self.switch_to(Scene::Playing);
+                return;
+            }
+            let confirmed = Self::update_menu(&mut self.pause_menu, pressed);
+            if let PauseItem::Setting(setting) = self.pause_menu.current() {
+                // Left and right change the value, confirming cycles through the values.
+                for (button, delta) in [(BUTTON_LEFT, -1), (BUTTON_RIGHT, 1)] {
+                    if pressed.gamepad & button != 0 {
+                        self.sky
+                            .update_settings(|settings| setting.change(settings, delta));
+                    }
+                }
+            }
+            if !confirmed {
+                return;
+            }
+            match self.pause_menu.current() {
+                PauseItem::Resume => self.switch_to(Scene::Playing),
+                PauseItem::Setting(setting) => {
+                    self.sky.update_settings(|settings| setting.cycle(settings))
+                }
+                PauseItem::QuitToTitle => {
+                    self.title_menu = title_menu(self.sky.resumable);
+                    self.switch_to(Scene::Title);
+                }
+            }
+        }
+
+        fn update_results(&mut self, pressed: &Pressed) {
+            if pressed.gamepad & BUTTON_1 != 0 || pressed.mouse & MOUSE_LEFT != 0 {
+                // Suggest a seed derived from the previous one.
+                let mut rng = self.sky.seed();
+                self.title_menu = title_menu(self.sky.resumable);
+                self.start_seed_selection(next_random(&mut rng));
+            }
+        }
+
+        #[skylite_proc::pre_update]
+        fn pre_update(&mut self, controls: &mut ProjectControls<Aoc>) {
+            self.frame = self.frame.wrapping_add(1);
+            self.transition = self.transition.saturating_sub(1);
+
+            let pressed = self.read_pressed();
+            let prev_scene = self.scene;
+            match self.scene {
+                Scene::Title => self.update_title(&pressed),
+                Scene::SeedSelection => self.update_seed_selection(&pressed),
+                Scene::Playing => self.update_playing(&pressed, controls.get_focus()),
+                Scene::Overview => self.update_overview(&pressed),
+                Scene::Paused => self.update_paused(&pressed),
+                Scene::Results => self.update_results(&pressed),
+            }
+
+            // Only the gameplay scene passes input on to the sky. The click
+            // that switched to or from it is not passed on.
+            self.sky.active = prev_scene == Scene::Playing && self.scene == Scene::Playing;
+            self.sky.set_results_visible(self.scene == Scene::Results);
+        }
+
+        #[skylite_proc::render]
+        fn render(&self, ctx: &mut RenderControls<Aoc>) {
+            let fb = &mut Wasm4Framebuffer;
+            let view = View::from_controls(ctx);
+            match self.scene {
+                Scene::Title => render_menu(
+                    "THE ART OF CONSTELLATION",
+                    &self.title_menu,
+                    title_label,
+                    fb,
+                ),
+                Scene::SeedSelection => render_seed_selection(&self.seed_selection, fb),
+                Scene::Playing => {
+                    render_minimap(self.sky.game(), &view, fb);
+                    render_pause_button(fb);
+                }
+                Scene::Overview => {
+                    // The rectangle shows where the camera moves when the overview is closed.
+                    let game = self.sky.game();
+                    let (x, y) = self.overview_pos;
+                    let (focus_x, focus_y) = focus_from_overview(&game.params, x, y);
+                    render_overview(
+                        game,
+                        &View {
+                            focus_x,
+                            focus_y,
+                            ..view
+                        },
+                        fb,
+                    );
+                }
+                Scene::Paused => {
+                    let settings = self.sky.settings();
+                    let label = |item| pause_label(item, settings);
+                    render_menu("PAUSED", &self.pause_menu, label, fb)
+                }
+                Scene::Results => {}
+            }
+            render_transition(self.transition, fb);
+        }
+
+        #[skylite_proc::z_order]
+        fn z_order(&self) -> i32 {
+            30
+        }
+    }
+}
+pub(crate) use app::*;
@@ -0,0 +1,192 @@
+use crate::framebuffer::SCREEN_SIZE;
+use crate::util::SkyParams;
+
+/// Share of the remaining distance that the camera covers per frame
+/// while easing towards a target.
+const EASING: f32 = 1.0 / 6.0;
+
+/// Share of its speed that the camera keeps per frame after a drag.
+const FRICTION: f32 = 0.9;
+
+/// Speeds below this stop the camera, in pixels per frame.
+const MIN_SPEED: f32 = 0.1;
+
+/// Distance from the border of the screen that `keep_in_view` keeps a point at.
+const VIEW_MARGIN: f32 = 40.0;
+
+/// The position of the screen in the sky, i.e. the focus. It moves with
+/// sub-pixel precision, keeps moving for a while after it was dragged and
+/// stays within the sky.
+pub(crate) struct Camera {
+    x: f32,
+    y: f32,
+    max_x: f32,
+    max_y: f32,
+    /// Movement per frame, kept after a drag ends.
+    velocity: (f32, f32),
+    /// The focus the camera is easing towards, if any.
+    target: Option<(f32, f32)>,
+    dragging: bool,
+}
+
+impl Camera {
+    pub(crate) fn new(params: &SkyParams) -> Camera {
+        Camera {
+            x: 0.0,
+            y: 0.0,
+            max_x: params.width().saturating_sub(SCREEN_SIZE) as f32,
+            max_y: params.height().saturating_sub(SCREEN_SIZE) as f32,
+            velocity: (0.0, 0.0),
+            target: None,
+            dragging: false,
+        }
+    }
+
+    pub(crate) fn focus(&self) -> (i32, i32) {
+        (self.x as i32, self.y as i32)
+    }
+
+    fn clamp(&self, x: f32, y: f32) -> (f32, f32) {
+        (x.clamp(0.0, self.max_x), y.clamp(0.0, self.max_y))
+    }
+
+    /// Move right away, e.g. when scrolling at the edge of the screen.
+    /// Any movement cancels easing.
+    pub(crate) fn scroll_by(&mut self, dx: f32, dy: f32) {
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+        self.target = None;
+        (self.x, self.y) = self.clamp(self.x + dx, self.y + dy);
+    }
+
+    /// Move with the pointer while it is dragging the sky. This has to be
+    /// called on every frame of the drag, the last movement is kept as the
+    /// speed once the drag ends.
+    pub(crate) fn drag_by(&mut self, dx: f32, dy: f32) {
+        self.dragging = true;
+        self.velocity = (dx, dy);
+        self.scroll_by(dx, dy);
+    }
+
+    pub(crate) fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub(crate) fn end_drag(&mut self) {
+        self.dragging = false;
+    }
+
+    /// Move smoothly to the given focus.
+    pub(crate) fn ease_to(&mut self, x: i32, y: i32) {
+        self.velocity = (0.0, 0.0);
+        self.target = Some(self.clamp(x as f32, y as f32));
+    }
+
+    /// Ease towards a focus that shows `(x, y)` at least `VIEW_MARGIN` pixels
+    /// away from the border of the screen. If the camera is already easing
+    /// towards such a focus, it keeps its target.
+    pub(crate) fn keep_in_view(&mut self, x: i16, y: i16) {
+        let target = |focus: f32, pos: i16| {
+            let pos = pos as f32;
+            focus
+                .max(pos - (SCREEN_SIZE as f32 - VIEW_MARGIN))
+                .min(pos - VIEW_MARGIN)
+        };
+        let (focus_x, focus_y) = self.target.unwrap_or((self.x, self.y));
+        let (target_x, target_y) = (target(focus_x, x), target(focus_y, y));
+        if (target_x, target_y) != (focus_x, focus_y) {
+            self.target = Some(self.clamp(target_x, target_y));
+        }
+    }
+
+    /// Advance the movement by one frame.
+    pub(crate) fn update(&mut self) {
+        if let Some((target_x, target_y)) = self.target {
+            let (dx, dy) = (target_x - self.x, target_y - self.y);
+            if dx.abs() < 0.5 && dy.abs() < 0.5 {
+                (self.x, self.y) = (target_x, target_y);
+                self.target = None;
+            } else {
+                self.x += dx * EASING;
+                self.y += dy * EASING;
+            }
+        } else if !self.dragging {
+            let (vx, vy) = self.velocity;
+            (self.x, self.y) = self.clamp(self.x + vx, self.y + vy);
+            self.velocity = if vx.hypot(vy) * FRICTION < MIN_SPEED {
+                (0.0, 0.0)
+            } else {
+                (vx * FRICTION, vy * FRICTION)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_keeps_moving_and_stops() {
+        let mut camera = Camera::new(&SkyParams::STANDARD);
+        camera.ease_to(200, 200);
+        for _ in 0..100 {
+            camera.update();
+        }
+        assert_eq!(camera.focus(), (200, 200));
+
+        camera.drag_by(10.0, -4.0);
+        camera.update();
+        assert_eq!(camera.focus(), (210, 196));
+        camera.end_drag();
+
+        let mut prev = camera.focus();
+        camera.update();
+        assert!(camera.focus().0 > prev.0);
+        for _ in 0..200 {
+            prev = camera.focus();
+            camera.update();
+        }
+        assert_eq!(camera.focus(), prev);
+    }
+
+    #[test]
+    fn following_keeps_eased_target() {
+        let mut camera = Camera::new(&SkyParams::STANDARD);
+        camera.ease_to(300, 300);
+        // The pointer is in the center of the screen at the target.
+        for _ in 0..100 {
+            camera.keep_in_view(380, 380);
+            camera.update();
+        }
+        assert_eq!(camera.focus(), (300, 300));
+
+        // Moving the pointer out of view still moves the camera.
+        for _ in 0..100 {
+            camera.keep_in_view(500, 380);
+            camera.update();
+        }
+        assert_eq!(camera.focus(), (380, 300));
+    }
+
+    #[test]
+    fn stays_within_sky() {
+        let params = SkyParams::STANDARD;
+        let mut camera = Camera::new(&params);
+        camera.drag_by(-50.0, 10_000.0);
+        camera.end_drag();
+        for _ in 0..100 {
+            camera.update();
+        }
+        let max_y = (params.height() - SCREEN_SIZE) as i32;
+        assert_eq!(camera.focus(), (0, max_y));
+
+        camera.ease_to(10_000, -10_000);
+        for _ in 0..100 {
+            camera.update();
+        }
+        let max_x = (params.width() - SCREEN_SIZE) as i32;
+        assert_eq!(camera.focus(), (max_x, 0));
+    }
+}
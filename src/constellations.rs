@@ -0,0 +1,130 @@
+use crate::line::Link;
+
+/// Disjoint sets of stars that are connected by links.
+///
+/// Each set keeps the list of its stars and links, and every star knows the
+/// set it belongs to, so looking up the constellation of a star takes constant
+/// time. When two sets are merged, the members of the smaller set are moved
+/// into the larger one. Links cannot be removed, instead the sets have to be
+/// rebuilt with `Constellations::new`.
+#[derive(Clone, Debug)]
+pub(crate) struct Constellations {
+    /// Set of each star.
+    set_of: Vec<u16>,
+    /// Stars of each set, empty for sets that were merged into another one.
+    stars: Vec<Vec<u16>>,
+    /// Indices of the links within each set.
+    links: Vec<Vec<u16>>,
+}
+
+impl Constellations {
+    pub(crate) fn new(star_count: usize, links: &[Link]) -> Constellations {
+        let mut out = Constellations {
+            set_of: Vec::with_capacity(star_count),
+            stars: Vec::with_capacity(star_count),
+            links: Vec::with_capacity(star_count),
+        };
+        for _ in 0..star_count {
+            out.add_star();
+        }
+        for (link_idx, link) in links.iter().enumerate() {
+            out.add_link(link_idx, link);
+        }
+        out
+    }
+
+    /// Add a star that is not connected to any other star yet.
+    pub(crate) fn add_star(&mut self) {
+        let idx = self.set_of.len() as u16;
+        self.set_of.push(idx);
+        self.stars.push(vec![idx]);
+        self.links.push(Vec::new());
+    }
+
+    /// Register a link that was pushed to the links at `link_idx`.
+    pub(crate) fn add_link(&mut self, link_idx: usize, link: &Link) {
+        let mut set_a = self.set_of[link.start_idx as usize] as usize;
+        let mut set_b = self.set_of[link.end_idx as usize] as usize;
+        if set_a != set_b {
+            if self.stars[set_a].len() < self.stars[set_b].len() {
+                std::mem::swap(&mut set_a, &mut set_b);
+            }
+
+            let moved_stars = std::mem::take(&mut self.stars[set_b]);
+            for star_idx in &moved_stars {
+                self.set_of[*star_idx as usize] = set_a as u16;
+            }
+            self.stars[set_a].extend(moved_stars);
+
+            let moved_links = std::mem::take(&mut self.links[set_b]);
+            self.links[set_a].extend(moved_links);
+        }
+        self.links[set_a].push(link_idx as u16);
+    }
+
+    /// Stars in the constellation of `star_idx`, including `star_idx` itself.
+    pub(crate) fn stars(&self, star_idx: usize) -> &[u16] {
+        &self.stars[self.set_of[star_idx] as usize]
+    }
+
+    /// Indices of the links in the constellation of `star_idx`.
+    pub(crate) fn links(&self, star_idx: usize) -> &[u16] {
+        &self.links[self.set_of[star_idx] as usize]
+    }
+
+    /// Number of stars in the constellation of `star_idx`.
+    pub(crate) fn size(&self, star_idx: usize) -> usize {
+        self.stars(star_idx).len()
+    }
+
+    /// Whether two stars are part of the same constellation.
+    pub(crate) fn are_connected(&self, a: usize, b: usize) -> bool {
+        self.set_of[a] == self.set_of[b]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::STYLE_BRIGHT;
+
+    #[test]
+    fn merges_constellations() {
+        let links = vec![
+            Link::new(0, 1, STYLE_BRIGHT),
+            Link::new(2, 3, STYLE_BRIGHT),
+            Link::new(3, 4, STYLE_BRIGHT),
+        ];
+        let mut constellations = Constellations::new(6, &links);
+        assert_eq!(constellations.size(0), 2);
+        assert_eq!(constellations.size(4), 3);
+        assert_eq!(constellations.size(5), 1);
+        assert!(!constellations.are_connected(1, 2));
+
+        let link = Link::new(1, 4, STYLE_BRIGHT);
+        constellations.add_link(3, &link);
+        assert!(constellations.are_connected(0, 3));
+        assert_eq!(constellations.size(2), 5);
+
+        let mut stars = constellations.stars(0).to_vec();
+        stars.sort();
+        assert_eq!(stars, vec![0, 1, 2, 3, 4]);
+        let mut link_indices = constellations.links(4).to_vec();
+        link_indices.sort();
+        assert_eq!(link_indices, vec![0, 1, 2, 3]);
+        assert!(constellations.links(5).is_empty());
+    }
+
+    #[test]
+    fn links_within_constellation() {
+        // Closing a loop does not merge anything, but the link is still tracked.
+        let links = vec![
+            Link::new(0, 1, STYLE_BRIGHT),
+            Link::new(1, 2, STYLE_BRIGHT),
+            Link::new(2, 0, STYLE_BRIGHT),
+        ];
+        let constellations = Constellations::new(3, &links);
+        assert_eq!(constellations.size(0), 3);
+        assert_eq!(constellations.links(1).len(), 3);
+    }
+}
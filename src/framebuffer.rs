@@ -0,0 +1,173 @@
+#[cfg(test)]
+use crate::palette::Palette;
+
+pub(crate) const SCREEN_SIZE: usize = 160;
+
+/// Size of a 2bpp framebuffer in bytes.
+pub(crate) const FRAMEBUFFER_SIZE: usize = SCREEN_SIZE * SCREEN_SIZE / 4;
+
+/// The part of the sky that is being rendered, and the frame
+/// counter used for animations.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct View {
+    pub focus_x: i32,
+    pub focus_y: i32,
+    pub frame: u32,
+    /// Draw animated lines and markers without movement.
+    pub reduce_motion: bool,
+}
+
+/// A 160x160 framebuffer with 2 bits per pixel, in the same layout
+/// as the WASM-4 framebuffer: Four pixels per byte, with the leftmost
+/// pixel in the lowest bits.
+///
+/// All drawing operations clip against the screen, and color 0 is
+/// treated as transparent for sprites.
+pub(crate) trait Framebuffer {
+    fn bytes_mut(&mut self) -> &mut [u8; FRAMEBUFFER_SIZE];
+
+    fn clear(&mut self) {
+        self.bytes_mut().fill(0);
+    }
+
+    fn set_pixel(&mut self, x: i32, y: i32, color: u8) {
+        if x < 0 || x >= SCREEN_SIZE as i32 || y < 0 || y >= SCREEN_SIZE as i32 {
+            return;
+        }
+
+        let pixel_idx = (y * SCREEN_SIZE as i32 + x) as usize;
+        let byte = pixel_idx >> 2;
+        let shift = (pixel_idx & 0b11) * 2;
+
+        let bytes = self.bytes_mut();
+        bytes[byte] &= !(0b11 << shift);
+        bytes[byte] |= (color & 0b11) << shift;
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: u8) {
+        for py in y..y + height as i32 {
+            for px in x..x + width as i32 {
+                self.set_pixel(px, py, color);
+            }
+        }
+    }
+
+    /// Draw a 2bpp sprite with its top-left corner at `(x, y)`.
+    /// The pixels are packed with the first pixel in the highest bits,
+    /// trailing data (like the width) is ignored.
+    fn blit_2bpp(&mut self, sprite: &[u8], x: i32, y: i32, width: u32, height: u32) {
+        if !is_on_screen(x, y, width, height) {
+            return;
+        }
+
+        for idx in 0..width * height {
+            let byte = sprite[(idx >> 2) as usize];
+            let color = (byte >> (6 - (idx & 0b11) * 2)) & 0b11;
+            if color != 0 {
+                self.set_pixel(x + (idx % width) as i32, y + (idx / width) as i32, color);
+            }
+        }
+    }
+
+    /// Draw a 1bpp sprite with its top-left corner at `(x, y)`.
+    /// Set bits are drawn with `color`, the first pixel is the highest bit.
+    fn blit_1bpp(&mut self, sprite: &[u8], x: i32, y: i32, width: u32, height: u32, color: u8) {
+        if !is_on_screen(x, y, width, height) {
+            return;
+        }
+
+        for idx in 0..width * height {
+            let byte = sprite[(idx >> 3) as usize];
+            if byte & (0x80 >> (idx & 0b111)) != 0 {
+                self.set_pixel(x + (idx % width) as i32, y + (idx / width) as i32, color);
+            }
+        }
+    }
+}
+
+fn is_on_screen(x: i32, y: i32, width: u32, height: u32) -> bool {
+    x + (width as i32) > 0
+        && x < SCREEN_SIZE as i32
+        && y + (height as i32) > 0
+        && y < SCREEN_SIZE as i32
+}
+
+/// The framebuffer provided by WASM-4.
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct Wasm4Framebuffer;
+
+#[cfg(target_arch = "wasm32")]
+impl Framebuffer for Wasm4Framebuffer {
+    fn bytes_mut(&mut self) -> &mut [u8; FRAMEBUFFER_SIZE] {
+        unsafe { &mut *wasm4_target::FRAMEBUFFER }
+    }
+}
+
+/// An in-memory framebuffer, used for rendering in tests.
+#[cfg(test)]
+pub(crate) struct MemoryFramebuffer {
+    bytes: [u8; FRAMEBUFFER_SIZE],
+}
+
+#[cfg(test)]
+impl MemoryFramebuffer {
+    pub(crate) fn new() -> MemoryFramebuffer {
+        MemoryFramebuffer {
+            bytes: [0; FRAMEBUFFER_SIZE],
+        }
+    }
+
+    pub(crate) fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        let pixel_idx = y * SCREEN_SIZE + x;
+        (self.bytes[pixel_idx >> 2] >> ((pixel_idx & 0b11) * 2)) & 0b11
+    }
+
+    /// Encode the framebuffer as a binary PGM image, with one gray level per color.
+    pub(crate) fn to_pgm(&self) -> Vec<u8> {
+        let mut out = format!("P5\n{SCREEN_SIZE} {SCREEN_SIZE}\n3\n").into_bytes();
+        for y in 0..SCREEN_SIZE {
+            for x in 0..SCREEN_SIZE {
+                out.push(self.get_pixel(x, y));
+            }
+        }
+        out
+    }
+
+    /// Encode the framebuffer as a binary PPM image, in the colours of `palette`.
+    pub(crate) fn to_ppm(&self, palette: &Palette) -> Vec<u8> {
+        let mut out = format!("P6\n{SCREEN_SIZE} {SCREEN_SIZE}\n255\n").into_bytes();
+        for y in 0..SCREEN_SIZE {
+            for x in 0..SCREEN_SIZE {
+                let color = palette[self.get_pixel(x, y) as usize];
+                out.extend_from_slice(&color.to_be_bytes()[1..]);
+            }
+        }
+        out
+    }
+
+    /// Decode an image written by `to_pgm`.
+    pub(crate) fn from_pgm(data: &[u8]) -> Option<MemoryFramebuffer> {
+        let header = format!("P5\n{SCREEN_SIZE} {SCREEN_SIZE}\n3\n").into_bytes();
+        let pixels = data.strip_prefix(header.as_slice())?;
+        if pixels.len() != SCREEN_SIZE * SCREEN_SIZE {
+            return None;
+        }
+
+        let mut out = MemoryFramebuffer::new();
+        for (idx, color) in pixels.iter().enumerate() {
+            out.set_pixel(
+                (idx % SCREEN_SIZE) as i32,
+                (idx / SCREEN_SIZE) as i32,
+                *color,
+            );
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+impl Framebuffer for MemoryFramebuffer {
+    fn bytes_mut(&mut self) -> &mut [u8; FRAMEBUFFER_SIZE] {
+        &mut self.bytes
+    }
+}
@@ -0,0 +1,1225 @@
+use crate::constellations::Constellations;
+use crate::history::{History, LinkAction};
+use crate::line::{Link, STYLE_BRIGHT, STYLE_DIM};
+use crate::rules::{LinkRejection, check_link};
+use crate::solver::find_completable_constellation;
+use crate::spatial::SpatialIndex;
+use crate::star::Star;
+use crate::util::{SkyParams, dim_lonely_stars, dim_lonely_stars_near, generate_sky};
+
+/// Amount of light the player starts with.
+pub(crate) const INITIAL_LIGHT: u8 = 8;
+
+/// Distance from a star at which the pointer still hits it, on both axes.
+/// The accessible mode uses a larger hit box.
+pub(crate) const STAR_HIT_RADIUS: i32 = 3;
+pub(crate) const ACCESSIBLE_STAR_HIT_RADIUS: i32 = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Input for the game, already translated into sky coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum InputEvent {
+    MoveTo {
+        x: i16,
+        y: i16,
+    },
+    /// Move the pointer to the nearest bright star in a direction.
+    Jump(Direction),
+    Press,
+    Release,
+    /// Abort the current draft without adding a link.
+    Cancel,
+    Undo,
+    Redo,
+}
+
+/// State changes caused by an `InputEvent`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum GameEvent {
+    DraftStarted {
+        star_idx: u16,
+    },
+    DraftDropped,
+    /// The player aborted the draft, which ended at `(end_x, end_y)`.
+    DraftCancelled {
+        start_idx: u16,
+        end_x: i16,
+        end_y: i16,
+    },
+    LinkAdded {
+        start_idx: u16,
+        end_idx: u16,
+    },
+    /// The draft was released on a star, but the link breaks the rules.
+    LinkRejected {
+        reason: LinkRejection,
+    },
+    LinkRemoved {
+        start_idx: u16,
+        end_idx: u16,
+    },
+    /// The constellation of `star_idx` was completed and refunded `light`.
+    ConstellationCompleted {
+        star_idx: u16,
+        star_count: usize,
+        light: u8,
+    },
+    GameOver {
+        outcome: Outcome,
+    },
+}
+
+/// How a finished round ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Outcome {
+    /// Every bright star was used up.
+    Won,
+    /// Bright stars are left, but none of them can become part of a
+    /// constellation with the remaining light.
+    Lost,
+}
+
+/// Summary of a finished round, shown on the results screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct GameResults {
+    pub outcome: Outcome,
+    pub completed_constellations: u16,
+    pub stars_used: u16,
+    pub light: u8,
+}
+
+/// A line that is currently being drawn by the player.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Draft {
+    pub start_idx: u16,
+    pub end_x: i16,
+    pub end_y: i16,
+    /// Whether the line was moved away from its start star. Moving back
+    /// to the start star afterwards cancels the draft.
+    pub left_origin: bool,
+}
+
+/// The complete state of a round, independent of the WASM-4 target.
+pub(crate) struct Game {
+    pub seed: u32,
+    pub params: SkyParams,
+    pub index: SpatialIndex,
+    pub stars: Vec<Star>,
+    pub links: Vec<Link>,
+    /// The stars and links of each constellation, kept in sync with `links`.
+    pub constellations: Constellations,
+    /// Number of links created by `generate_sky`. All links after these
+    /// were added by the player.
+    pub preset_link_count: usize,
+    pub light: u8,
+    pub completed_constellations: u16,
+    /// Number of stars in all completed constellations.
+    pub stars_used: u16,
+    pub draft: Option<Draft>,
+    pub history: History,
+    /// See `get_bright_star_idx_at`.
+    pub star_hit_radius: i32,
+    /// The links that completed some constellation when the solver last ran.
+    /// The solver is expensive on large skies, so this is reused for as long
+    /// as it still works, see `can_complete`.
+    plan: Option<Vec<(u16, u16)>>,
+    pointer_x: i16,
+    pointer_y: i16,
+}
+
+impl Game {
+    pub(crate) fn new(seed: u32, params: SkyParams) -> Game {
+        debug_assert!(params.is_valid());
+        let (sections, mut stars, mut links) = generate_sky(&params, seed);
+        // Stars that can never be part of a constellation are dimmed right
+        // away, so that later only the neighborhood of a completed
+        // constellation has to be checked.
+        dim_lonely_stars(&params, &sections, &mut stars, &mut links);
+        let constellations = Constellations::new(stars.len(), &links);
+        Game {
+            seed,
+            index: SpatialIndex::new(&params, sections),
+            params,
+            stars,
+            preset_link_count: links.len(),
+            constellations,
+            links,
+            light: INITIAL_LIGHT,
+            completed_constellations: 0,
+            stars_used: 0,
+            draft: None,
+            history: History::new(),
+            star_hit_radius: STAR_HIT_RADIUS,
+            plan: None,
+            pointer_x: 0,
+            pointer_y: 0,
+        }
+    }
+
+    /// The outcome of the round, or `None` while it is still running.
+    ///
+    /// The round is won once every star is used up, and lost as soon as no
+    /// constellation can be completed with the remaining light.
+    pub(crate) fn outcome(&self) -> Option<Outcome> {
+        if !self.stars.iter().any(|star| star.bright) {
+            Some(Outcome::Won)
+        } else if self.has_plan() || self.find_completable_constellation().is_some() {
+            None
+        } else {
+            Some(Outcome::Lost)
+        }
+    }
+
+    /// Whether adding the links of `plan` still completes a constellation,
+    /// i.e. the links are allowed, there is enough light for them and they
+    /// connect at least `constellation_threshold` bright stars.
+    fn can_complete(&self, plan: &[(u16, u16)]) -> bool {
+        let Some((first_idx, _)) = plan.first() else {
+            return false;
+        };
+        let is_bright = |(start_idx, end_idx): &(u16, u16)| {
+            self.stars[*start_idx as usize].bright && self.stars[*end_idx as usize].bright
+        };
+        // Links of the plan that the player already added are not needed anymore.
+        let is_missing = |(start_idx, end_idx): &&(u16, u16)| {
+            !self
+                .constellations
+                .are_connected(*start_idx as usize, *end_idx as usize)
+        };
+        let is_allowed = |(start_idx, end_idx): &&(u16, u16)| {
+            check_link(
+                &self.params.link_rules,
+                &self.stars,
+                &self.links,
+                *start_idx,
+                *end_idx,
+            )
+            .is_ok()
+        };
+        let missing: Vec<&(u16, u16)> = plan.iter().filter(is_missing).collect();
+        if !plan.iter().all(is_bright)
+            || missing.len() > self.light as usize
+            || !missing.iter().all(is_allowed)
+        {
+            return false;
+        }
+
+        // Undoing links can split the constellations that the plan connects,
+        // so only count the ones that are still connected to the first star.
+        let mut connected = vec![*first_idx as usize];
+        let mut size = self.constellations.size(*first_idx as usize);
+        let is_connected = |connected: &[usize], idx: u16| {
+            connected
+                .iter()
+                .any(|other| self.constellations.are_connected(*other, idx as usize))
+        };
+        loop {
+            let next = plan.iter().find_map(|(start_idx, end_idx)| {
+                match (
+                    is_connected(&connected, *start_idx),
+                    is_connected(&connected, *end_idx),
+                ) {
+                    (true, false) => Some(*end_idx as usize),
+                    (false, true) => Some(*start_idx as usize),
+                    _ => None,
+                }
+            });
+            let Some(next) = next else {
+                return size >= self.params.constellation_threshold;
+            };
+            connected.push(next);
+            size += self.constellations.size(next);
+        }
+    }
+
+    fn has_plan(&self) -> bool {
+        self.plan
+            .as_ref()
+            .is_some_and(|plan| self.can_complete(plan))
+    }
+
+    /// Look for a new plan unless the current one still works.
+    fn update_plan(&mut self) {
+        if !self.has_plan() {
+            self.plan = self.find_completable_constellation();
+        }
+    }
+
+    /// The links needed to complete some constellation, see
+    /// `solver::find_completable_constellation`.
+    pub(crate) fn find_completable_constellation(&self) -> Option<Vec<(u16, u16)>> {
+        find_completable_constellation(
+            &self.params,
+            &self.index.sections,
+            &self.stars,
+            &self.links,
+            &self.constellations,
+            self.light,
+        )
+    }
+
+    pub(crate) fn results(&self) -> Option<GameResults> {
+        Some(GameResults {
+            outcome: self.outcome()?,
+            completed_constellations: self.completed_constellations,
+            stars_used: self.stars_used,
+            light: self.light,
+        })
+    }
+
+    /// The bright star closest to `(x, y)`, within `star_hit_radius` on both axes.
+    pub(crate) fn get_bright_star_idx_at(&self, x: i16, y: i16) -> Option<usize> {
+        let (x, y) = (x as i32, y as i32);
+        let radius = self.star_hit_radius;
+        self.index
+            .stars_in_rect(x - radius, y - radius, x + radius, y + radius)
+            .filter_map(|idx| {
+                let star = &self.stars[idx];
+                let dx = star.x as i32 - x;
+                let dy = star.y as i32 - y;
+                let hit = star.bright && dx.abs() <= radius && dy.abs() <= radius;
+                hit.then_some((dx * dx + dy * dy, idx))
+            })
+            .min()
+            .map(|(_, idx)| idx)
+    }
+
+    /// The position used for hit-testing. While a line is drafted,
+    /// this is the (possibly limited or snapped) end of the line.
+    pub(crate) fn effective_pointer_pos(&self) -> (i16, i16) {
+        match &self.draft {
+            Some(draft) => (draft.end_x, draft.end_y),
+            None => (self.pointer_x, self.pointer_y),
+        }
+    }
+
+    /// The bright star the cursor should be shown on, if any.
+    pub(crate) fn cursor_star(&self) -> Option<usize> {
+        let (x, y) = self.effective_pointer_pos();
+        self.get_bright_star_idx_at(x, y)
+    }
+
+    /// Stars within line distance of the start of the current draft that
+    /// it can be linked to, i.e. the stars that the solver also considers.
+    pub(crate) fn draft_targets(&self) -> Vec<usize> {
+        let Some(draft) = self.draft else {
+            return Vec::new();
+        };
+        let start = &self.stars[draft.start_idx as usize];
+        let max_dist = self.params.star_dist_max_for_line as i32;
+        self.index
+            .stars_within(&self.stars, start.x, start.y, max_dist)
+            .filter(|idx| {
+                *idx != draft.start_idx as usize
+                    && self.stars[*idx].bright
+                    && check_link(
+                        &self.params.link_rules,
+                        &self.stars,
+                        &self.links,
+                        draft.start_idx,
+                        *idx as u16,
+                    )
+                    .is_ok()
+            })
+            .collect()
+    }
+
+    /// Why the current draft cannot be linked to the star below the
+    /// pointer, if it ends on such a star.
+    pub(crate) fn draft_rejection(&self) -> Option<LinkRejection> {
+        let draft = self.draft?;
+        let end_idx = self.cursor_star()?;
+        if end_idx == draft.start_idx as usize {
+            return None;
+        }
+        check_link(
+            &self.params.link_rules,
+            &self.stars,
+            &self.links,
+            draft.start_idx,
+            end_idx as u16,
+        )
+        .err()
+    }
+
+    /// Find the bright star that is closest to `(x, y)` in `direction`.
+    /// Stars off to the side count as further away than stars straight
+    /// ahead. While a line is drafted, only stars that can be linked to
+    /// the start of the line are considered.
+    fn find_star_in_direction(&self, x: i16, y: i16, direction: Direction) -> Option<usize> {
+        let score = |idx: usize| {
+            let star = &self.stars[idx];
+            let dx = (star.x - x) as i32;
+            let dy = (star.y - y) as i32;
+            let (along, across) = match direction {
+                Direction::Left => (-dx, dy),
+                Direction::Right => (dx, dy),
+                Direction::Up => (-dy, dx),
+                Direction::Down => (dy, dx),
+            };
+            if !star.bright || along <= 0 {
+                None
+            } else {
+                Some(along + 2 * across.abs())
+            }
+        };
+
+        if self.draft.is_some() {
+            return self
+                .draft_targets()
+                .into_iter()
+                .filter_map(|idx| Some((score(idx)?, idx)))
+                .min()
+                .map(|(_, idx)| idx);
+        }
+
+        // The score is never smaller than the distance, so the search can
+        // stop once the remaining sections are further away than the best star.
+        let mut best: Option<(i32, usize)> = None;
+        for ring in 0..self.index.ring_count() {
+            if best.is_some_and(|(best_score, _)| best_score <= self.index.min_ring_distance(ring))
+            {
+                break;
+            }
+            for idx in self.index.stars_in_ring(x, y, ring) {
+                if let Some(score) = score(idx)
+                    && best.is_none_or(|best| (score, idx) < best)
+                {
+                    best = Some((score, idx));
+                }
+            }
+        }
+        best.map(|(_, idx)| idx)
+    }
+
+    fn move_pointer(&mut self, x: i16, y: i16, events: &mut Vec<GameEvent>) {
+        self.pointer_x = x;
+        self.pointer_y = y;
+        let Some(draft) = &mut self.draft else {
+            return;
+        };
+        let start_idx = draft.start_idx as usize;
+        draft.end_x = x;
+        draft.end_y = y;
+        self.draft_line_limit_len();
+        self.draft_line_snap();
+
+        if self.cursor_star() != Some(start_idx) {
+            if let Some(draft) = &mut self.draft {
+                draft.left_origin = true;
+            }
+        } else if self.draft.is_some_and(|draft| draft.left_origin) {
+            self.cancel_draft(events);
+        }
+    }
+
+    fn cancel_draft(&mut self, events: &mut Vec<GameEvent>) {
+        if let Some(draft) = self.draft.take() {
+            events.push(GameEvent::DraftCancelled {
+                start_idx: draft.start_idx,
+                end_x: draft.end_x,
+                end_y: draft.end_y,
+            });
+        }
+    }
+
+    fn draft_line_limit_len(&mut self) {
+        let Some(draft) = &mut self.draft else {
+            return;
+        };
+        let start_star = &self.stars[draft.start_idx as usize];
+        let start_x = start_star.x;
+        let start_y = start_star.y;
+
+        let dx = draft.end_x as i32 - start_x as i32;
+        let dy = draft.end_y as i32 - start_y as i32;
+        let dist = ((dx * dx + dy * dy) as f32).sqrt();
+
+        let max_dist = self.params.star_dist_max_for_line as f32;
+        if dist > max_dist {
+            let scale = max_dist / dist;
+            draft.end_x = start_x + (dx as f32 * scale) as i16;
+            draft.end_y = start_y + (dy as f32 * scale) as i16;
+        }
+    }
+
+    /// Snap the end of the draft line to a star, if it is close enough.
+    /// We do not limit the line length after this,
+    /// because there should never be a case where the snapping
+    /// would create a line that is too long. This would mean
+    /// that the generated sky contains stars whose distance
+    /// is within the dead zone defined by [star_dist_max_for_line: star_dist_dead_zone_end),
+    /// which is checked by `validate_sky`.
+    fn draft_line_snap(&mut self) {
+        let Some(idx) = self.cursor_star() else {
+            return;
+        };
+        let (x, y) = (self.stars[idx].x, self.stars[idx].y);
+        if let Some(draft) = &mut self.draft {
+            draft.end_x = x;
+            draft.end_y = y;
+        }
+    }
+
+    /// Add a link and apply its side effects. Returns everything
+    /// that is needed to revert the link again.
+    fn apply_link(
+        &mut self,
+        start_idx: usize,
+        end_idx: usize,
+        events: &mut Vec<GameEvent>,
+    ) -> LinkAction {
+        let mut action = LinkAction {
+            start_idx: start_idx as u16,
+            end_idx: end_idx as u16,
+            light_before: self.light,
+            completed_size: 0,
+            dimmed_stars: Vec::new(),
+            dimmed_links: Vec::new(),
+        };
+
+        let link = Link::new(start_idx as u16, end_idx as u16, STYLE_BRIGHT);
+        self.constellations.add_link(self.links.len(), &link);
+        self.links.push(link);
+        self.light -= 1;
+        events.push(GameEvent::LinkAdded {
+            start_idx: start_idx as u16,
+            end_idx: end_idx as u16,
+        });
+
+        let size = self.constellations.size(end_idx);
+        if size >= self.params.constellation_threshold {
+            // Brilliant stars give back some extra light.
+            let bonus = self
+                .constellations
+                .stars(end_idx)
+                .iter()
+                .map(|idx| self.stars[*idx as usize].magnitude.light_bonus())
+                .fold(0, u8::saturating_add);
+            let refund = u8::try_from(size.saturating_sub(4))
+                .unwrap_or(u8::MAX)
+                .saturating_add(bonus);
+            self.light = self.light.saturating_add(refund);
+            self.completed_constellations += 1;
+            self.stars_used += size as u16;
+            action.completed_size = size as u16;
+            events.push(GameEvent::ConstellationCompleted {
+                star_idx: end_idx as u16,
+                star_count: size,
+                light: refund,
+            });
+
+            let constellation_stars = self.constellations.stars(end_idx).to_vec();
+            for star_idx in &constellation_stars {
+                self.stars[*star_idx as usize].bright = false;
+            }
+
+            let constellation_links = self.constellations.links(end_idx).to_vec();
+            for link_idx in &constellation_links {
+                self.links[*link_idx as usize].style = STYLE_DIM;
+            }
+
+            let (lonely_stars, lonely_links) = dim_lonely_stars_near(
+                &self.params,
+                &self.index.sections,
+                &mut self.stars,
+                &mut self.links,
+                &constellation_stars,
+            );
+
+            action.dimmed_stars = constellation_stars;
+            action.dimmed_stars.extend(lonely_stars);
+            action.dimmed_links = constellation_links;
+            action.dimmed_links.extend(lonely_links);
+        }
+
+        action
+    }
+
+    pub(crate) fn add_link(
+        &mut self,
+        start_idx: usize,
+        end_idx: usize,
+        events: &mut Vec<GameEvent>,
+    ) {
+        let action = self.apply_link(start_idx, end_idx, events);
+        self.history.push(action);
+    }
+
+    /// Revert the last link added by the player.
+    pub(crate) fn undo(&mut self, events: &mut Vec<GameEvent>) {
+        let Some(action) = self.history.pop_undo() else {
+            return;
+        };
+
+        for star_idx in &action.dimmed_stars {
+            self.stars[*star_idx as usize].bright = true;
+        }
+        for link_idx in &action.dimmed_links {
+            self.links[*link_idx as usize].style = STYLE_BRIGHT;
+        }
+        self.links.pop();
+        self.constellations = Constellations::new(self.stars.len(), &self.links);
+        self.light = action.light_before;
+        if action.completed_size > 0 {
+            self.completed_constellations -= 1;
+            self.stars_used -= action.completed_size;
+        }
+
+        events.push(GameEvent::LinkRemoved {
+            start_idx: action.start_idx,
+            end_idx: action.end_idx,
+        });
+        self.history.push_undone(action);
+    }
+
+    /// Add the last undone link again.
+    pub(crate) fn redo(&mut self, events: &mut Vec<GameEvent>) {
+        let Some(action) = self.history.pop_redo() else {
+            return;
+        };
+
+        let action = self.apply_link(action.start_idx as usize, action.end_idx as usize, events);
+        self.history.push_redone(action);
+    }
+
+    /// Apply a single input event and return the resulting state changes.
+    pub(crate) fn handle_input(&mut self, input: InputEvent) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        match input {
+            InputEvent::MoveTo { x, y } => self.move_pointer(x, y, &mut events),
+            InputEvent::Jump(direction) => {
+                let (x, y) = match self.cursor_star() {
+                    Some(idx) => (self.stars[idx].x, self.stars[idx].y),
+                    None => self.effective_pointer_pos(),
+                };
+                if let Some(idx) = self.find_star_in_direction(x, y, direction) {
+                    self.move_pointer(self.stars[idx].x, self.stars[idx].y, &mut events);
+                }
+            }
+            InputEvent::Press if self.light > 0 => {
+                if let Some(star_idx) = self.cursor_star() {
+                    self.draft = Some(Draft {
+                        start_idx: star_idx as u16,
+                        end_x: self.pointer_x,
+                        end_y: self.pointer_y,
+                        left_origin: false,
+                    });
+                    self.draft_line_snap();
+                    events.push(GameEvent::DraftStarted {
+                        star_idx: star_idx as u16,
+                    });
+                }
+            }
+            InputEvent::Release => {
+                let end_idx = self.cursor_star();
+                if let Some(draft) = self.draft.take() {
+                    match end_idx {
+                        Some(end_idx) if end_idx != draft.start_idx as usize && self.light > 0 => {
+                            match check_link(
+                                &self.params.link_rules,
+                                &self.stars,
+                                &self.links,
+                                draft.start_idx,
+                                end_idx as u16,
+                            ) {
+                                Ok(()) => {
+                                    self.add_link(draft.start_idx as usize, end_idx, &mut events)
+                                }
+                                Err(reason) => events.push(GameEvent::LinkRejected { reason }),
+                            }
+                        }
+                        _ => events.push(GameEvent::DraftDropped),
+                    }
+                }
+            }
+            InputEvent::Cancel => self.cancel_draft(&mut events),
+            // Undo and redo are ignored while a line is drafted.
+            InputEvent::Undo if self.draft.is_none() => self.undo(&mut events),
+            InputEvent::Redo if self.draft.is_none() => self.redo(&mut events),
+            InputEvent::Press | InputEvent::Undo | InputEvent::Redo => {}
+        }
+
+        let link_added = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::LinkAdded { .. }));
+        if link_added {
+            self.update_plan();
+            if let Some(outcome) = self.outcome() {
+                events.push(GameEvent::GameOver { outcome });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::rules::{LinkRules, segments_cross};
+    use crate::star::Magnitude;
+    use std::time::{Duration, Instant};
+
+    fn all_linkable_pairs(game: &Game) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let max_dist_sq = (game.params.star_dist_max_for_line.pow(2)) as i32;
+        (0..game.stars.len())
+            .flat_map(move |a| (0..game.stars.len()).map(move |b| (a, b)))
+            .filter(move |&(a, b)| {
+                let (star_a, star_b) = (&game.stars[a], &game.stars[b]);
+                let dx = (star_a.x - star_b.x) as i32;
+                let dy = (star_a.y - star_b.y) as i32;
+                a != b && star_a.bright && star_b.bright && dx * dx + dy * dy <= max_dist_sq
+            })
+    }
+
+    fn find_linkable_pair(game: &Game) -> (usize, usize) {
+        all_linkable_pairs(game)
+            .next()
+            .expect("Sky contains no linkable pair of stars")
+    }
+
+    /// Link two stars by dragging the pointer from one to the other.
+    pub(crate) fn drag(game: &mut Game, from: usize, to: usize) -> Vec<GameEvent> {
+        let (x1, y1) = (game.stars[from].x, game.stars[from].y);
+        let (x2, y2) = (game.stars[to].x, game.stars[to].y);
+        let mut events = game.handle_input(InputEvent::MoveTo { x: x1, y: y1 });
+        events.extend(game.handle_input(InputEvent::Press));
+        events.extend(game.handle_input(InputEvent::MoveTo { x: x2, y: y2 }));
+        events.extend(game.handle_input(InputEvent::Release));
+        events
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let a = Game::new(123456, SkyParams::STANDARD);
+        let b = Game::new(123456, SkyParams::STANDARD);
+        assert_eq!(a.stars, b.stars);
+        assert_eq!(a.links, b.links);
+    }
+
+    #[test]
+    fn dragging_between_stars_adds_link() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+        let link_count = game.links.len();
+
+        let events = drag(&mut game, a, b);
+
+        assert_eq!(events[0], GameEvent::DraftStarted { star_idx: a as u16 });
+        assert!(events.contains(&GameEvent::LinkAdded {
+            start_idx: a as u16,
+            end_idx: b as u16
+        }));
+        assert_eq!(game.links.len(), link_count + 1);
+        assert_eq!(game.light, INITIAL_LIGHT - 1);
+        assert_eq!(game.draft, None);
+    }
+
+    #[test]
+    fn releasing_on_empty_space_drops_draft() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, _) = find_linkable_pair(&game);
+        let (x, y) = (game.stars[a].x, game.stars[a].y);
+        let link_count = game.links.len();
+
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        game.handle_input(InputEvent::MoveTo { x: x + 10, y });
+        let events = game.handle_input(InputEvent::Release);
+
+        assert_eq!(events, vec![GameEvent::DraftDropped]);
+        assert_eq!(game.links.len(), link_count);
+        assert_eq!(game.light, INITIAL_LIGHT);
+    }
+
+    /// Find a pair of bright stars that completes a constellation when linked,
+    /// without breaking the link rules.
+    pub(crate) fn find_completing_pair(game: &Game) -> (usize, usize) {
+        let max_dist = game.params.star_dist_max_for_line as i32;
+        for a in 0..game.stars.len() {
+            let (x, y) = (game.stars[a].x, game.stars[a].y);
+            for b in game.index.stars_within(&game.stars, x, y, max_dist) {
+                let size = game.constellations.size(a) + game.constellations.size(b);
+                if game.stars[a].bright
+                    && game.stars[b].bright
+                    && !game.constellations.are_connected(a, b)
+                    && size >= game.params.constellation_threshold
+                    && check_link(
+                        &game.params.link_rules,
+                        &game.stars,
+                        &game.links,
+                        a as u16,
+                        b as u16,
+                    )
+                    .is_ok()
+                {
+                    return (a, b);
+                }
+            }
+        }
+        panic!("Sky contains no pair that completes a constellation");
+    }
+
+    #[test]
+    fn undo_restores_previous_state() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+        let (stars, links) = (game.stars.clone(), game.links.clone());
+
+        drag(&mut game, a, b);
+        let events = game.handle_input(InputEvent::Undo);
+
+        assert_eq!(
+            events,
+            vec![GameEvent::LinkRemoved {
+                start_idx: a as u16,
+                end_idx: b as u16
+            }]
+        );
+        assert_eq!(game.stars, stars);
+        assert_eq!(game.links, links);
+        assert_eq!(game.light, INITIAL_LIGHT);
+    }
+
+    #[test]
+    fn undo_reverts_completed_constellation() {
+        let params = SkyParams {
+            constellation_threshold: 4,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params);
+        let (a, b) = find_completing_pair(&game);
+        let (stars, links) = (game.stars.clone(), game.links.clone());
+
+        let events = drag(&mut game, a, b);
+        assert!(
+            events
+                .iter()
+                .any(|e| matches!(e, GameEvent::ConstellationCompleted { .. }))
+        );
+        let (completed_stars, completed_links, completed_light) =
+            (game.stars.clone(), game.links.clone(), game.light);
+
+        game.handle_input(InputEvent::Undo);
+        assert_eq!(game.stars, stars);
+        assert_eq!(game.links, links);
+        assert_eq!(game.light, INITIAL_LIGHT);
+
+        game.handle_input(InputEvent::Redo);
+        assert_eq!(game.stars, completed_stars);
+        assert_eq!(game.links, completed_links);
+        assert_eq!(game.light, completed_light);
+    }
+
+    #[test]
+    fn brilliant_stars_refund_extra_light() {
+        let params = SkyParams {
+            constellation_threshold: 4,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params.clone());
+        let mut brilliant_game = Game::new(123456, params);
+        let (a, b) = find_completing_pair(&game);
+        for star in game.stars.iter_mut().chain(brilliant_game.stars.iter_mut()) {
+            star.magnitude = Magnitude::Faint;
+        }
+        brilliant_game.stars[a].magnitude = Magnitude::Brilliant;
+
+        drag(&mut game, a, b);
+        drag(&mut brilliant_game, a, b);
+        assert_eq!(brilliant_game.light, game.light + 1);
+    }
+
+    #[test]
+    fn draft_targets_can_be_linked() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, _) = find_linkable_pair(&game);
+        assert!(game.draft_targets().is_empty());
+
+        game.handle_input(InputEvent::MoveTo {
+            x: game.stars[a].x,
+            y: game.stars[a].y,
+        });
+        game.handle_input(InputEvent::Press);
+        let targets = game.draft_targets();
+        assert!(!targets.is_empty());
+        for target in targets {
+            let rules = &game.params.link_rules;
+            assert!(check_link(rules, &game.stars, &game.links, a as u16, target as u16).is_ok());
+            assert!(target != a && game.stars[target].bright);
+        }
+    }
+
+    #[test]
+    fn larger_hit_box_finds_nearest_star() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let idx = game.stars.iter().position(|star| star.bright).unwrap();
+        let (x, y) = (game.stars[idx].x, game.stars[idx].y);
+        assert_eq!(game.get_bright_star_idx_at(x + 5, y - 5), None);
+
+        game.star_hit_radius = ACCESSIBLE_STAR_HIT_RADIUS;
+        assert_eq!(game.get_bright_star_idx_at(x + 5, y - 5), Some(idx));
+        assert_eq!(game.get_bright_star_idx_at(x, y), Some(idx));
+    }
+
+    #[test]
+    fn new_link_discards_redo() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+
+        drag(&mut game, a, b);
+        game.handle_input(InputEvent::Undo);
+        drag(&mut game, b, a);
+        let link_count = game.links.len();
+
+        assert!(game.handle_input(InputEvent::Redo).is_empty());
+        assert_eq!(game.links.len(), link_count);
+    }
+
+    #[test]
+    fn running_out_of_light_loses() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+        game.light = 1;
+
+        let events = drag(&mut game, a, b);
+        assert!(events.contains(&GameEvent::GameOver {
+            outcome: Outcome::Lost
+        }));
+        assert_eq!(game.light, 0);
+
+        // No further links can be drafted.
+        let (x, y) = (game.stars[b].x, game.stars[b].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        assert!(game.handle_input(InputEvent::Press).is_empty());
+        assert_eq!(game.light, 0);
+    }
+
+    #[test]
+    fn using_up_all_stars_wins() {
+        let params = SkyParams {
+            constellation_threshold: 4,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params);
+        let (a, b) = find_completing_pair(&game);
+        let constellation_a = game.constellations.stars(a).to_vec();
+        let constellation_b = game.constellations.stars(b).to_vec();
+        for (idx, star) in game.stars.iter_mut().enumerate() {
+            let idx = idx as u16;
+            star.bright &= constellation_a.contains(&idx) || constellation_b.contains(&idx);
+        }
+
+        let events = drag(&mut game, a, b);
+        assert!(events.contains(&GameEvent::GameOver {
+            outcome: Outcome::Won
+        }));
+        let results = game.results().unwrap();
+        assert_eq!(results.completed_constellations, 1);
+        assert_eq!(
+            results.stars_used as usize,
+            constellation_a.len() + constellation_b.len()
+        );
+    }
+
+    #[test]
+    fn stranded_stars_lose() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+        for (idx, star) in game.stars.iter_mut().enumerate() {
+            star.bright = idx == a || idx == b;
+        }
+
+        let events = drag(&mut game, a, b);
+        assert!(events.contains(&GameEvent::GameOver {
+            outcome: Outcome::Lost
+        }));
+        assert_eq!(game.light, INITIAL_LIGHT - 1);
+    }
+
+    #[test]
+    fn plan_is_kept_while_it_works() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        game.update_plan();
+        let plan = game.plan.clone().unwrap();
+
+        // Following the plan keeps it, also after undoing.
+        let (a, b) = plan[0];
+        drag(&mut game, a as usize, b as usize);
+        assert_eq!(game.plan.as_ref(), Some(&plan));
+        assert!(game.has_plan());
+        game.handle_input(InputEvent::Undo);
+        assert!(game.has_plan());
+
+        game.light = plan.len() as u8 - 1;
+        assert!(!game.has_plan());
+        game.light = INITIAL_LIGHT;
+        game.stars[b as usize].bright = false;
+        assert!(!game.has_plan());
+    }
+
+    fn jump(game: &mut Game, direction: Direction) -> (i16, i16) {
+        game.handle_input(InputEvent::Jump(direction));
+        let idx = game.cursor_star().unwrap();
+        (game.stars[idx].x, game.stars[idx].y)
+    }
+
+    #[test]
+    fn jump_moves_to_star_in_direction() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, _) = find_linkable_pair(&game);
+        let (x, y) = (game.stars[a].x, game.stars[a].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+
+        assert!(jump(&mut game, Direction::Right).0 > x);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        assert!(jump(&mut game, Direction::Left).0 < x);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        assert!(jump(&mut game, Direction::Down).1 > y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        assert!(jump(&mut game, Direction::Up).1 < y);
+    }
+
+    #[test]
+    fn linking_with_jumps() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+        let (x, y) = (game.stars[a].x, game.stars[a].y);
+        let direction = if game.stars[b].x > x {
+            Direction::Right
+        } else {
+            Direction::Left
+        };
+
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        game.handle_input(InputEvent::Jump(direction));
+
+        // Only stars that can be linked to the start are selected.
+        let end_idx = game.cursor_star().unwrap();
+        let (end_x, end_y) = (game.stars[end_idx].x, game.stars[end_idx].y);
+        assert_ne!(end_idx, a);
+        assert!(
+            ((end_x - x) as i32).pow(2) + ((end_y - y) as i32).pow(2)
+                <= (game.params.star_dist_max_for_line.pow(2)) as i32
+        );
+
+        let events = game.handle_input(InputEvent::Release);
+        assert!(events.contains(&GameEvent::LinkAdded {
+            start_idx: a as u16,
+            end_idx: end_idx as u16
+        }));
+    }
+
+    #[test]
+    fn cancel_drops_draft() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, b) = find_linkable_pair(&game);
+        let (x, y) = (game.stars[a].x, game.stars[a].y);
+        let link_count = game.links.len();
+
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        let (x, y) = (game.stars[b].x, game.stars[b].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+
+        assert_eq!(
+            game.handle_input(InputEvent::Cancel),
+            vec![GameEvent::DraftCancelled {
+                start_idx: a as u16,
+                end_x: x,
+                end_y: y
+            }]
+        );
+        assert_eq!(game.handle_input(InputEvent::Release), vec![]);
+        assert_eq!(game.links.len(), link_count);
+    }
+
+    #[test]
+    fn moving_back_to_origin_cancels_draft() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let (a, _) = find_linkable_pair(&game);
+        let (x, y) = (game.stars[a].x, game.stars[a].y);
+
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        // Small movements around the start star keep the draft.
+        assert!(
+            game.handle_input(InputEvent::MoveTo { x: x + 1, y })
+                .is_empty()
+        );
+        assert!(game.draft.is_some());
+
+        game.handle_input(InputEvent::MoveTo { x: x + 15, y });
+        let events = game.handle_input(InputEvent::MoveTo { x, y });
+        assert!(matches!(
+            events.as_slice(),
+            [GameEvent::DraftCancelled { .. }]
+        ));
+        assert_eq!(game.draft, None);
+    }
+
+    /// Find two pairs of stars whose links would cross each other.
+    fn find_crossing_pairs(game: &Game) -> ((usize, usize), (usize, usize)) {
+        let pos = |idx: usize| (game.stars[idx].x as i32, game.stars[idx].y as i32);
+        let pairs: Vec<_> = all_linkable_pairs(game).filter(|(a, b)| a < b).collect();
+        for &(a, b) in &pairs {
+            for &(c, d) in &pairs {
+                if ![c, d].contains(&a)
+                    && ![c, d].contains(&b)
+                    && segments_cross(pos(a), pos(b), pos(c), pos(d))
+                {
+                    return ((a, b), (c, d));
+                }
+            }
+        }
+        panic!("Sky contains no pairs of stars whose links cross");
+    }
+
+    #[test]
+    fn crossing_links_are_rejected() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let ((a, b), (c, d)) = find_crossing_pairs(&game);
+        assert!(drag(&mut game, a, b).contains(&GameEvent::LinkAdded {
+            start_idx: a as u16,
+            end_idx: b as u16
+        }));
+        let link_count = game.links.len();
+
+        let (x, y) = (game.stars[c].x, game.stars[c].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        let (x, y) = (game.stars[d].x, game.stars[d].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        assert!(matches!(
+            game.draft_rejection(),
+            Some(LinkRejection::Crossing { .. })
+        ));
+
+        let events = game.handle_input(InputEvent::Release);
+        assert!(matches!(
+            events.as_slice(),
+            [GameEvent::LinkRejected {
+                reason: LinkRejection::Crossing { .. }
+            }]
+        ));
+        assert_eq!(game.links.len(), link_count);
+        assert_eq!(game.light, INITIAL_LIGHT - 1);
+    }
+
+    #[test]
+    fn jumps_skip_rejected_stars() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let ((a, b), (c, d)) = find_crossing_pairs(&game);
+        drag(&mut game, a, b);
+        // Leave `d` as the only star that a jump from `c` could select.
+        for (idx, star) in game.stars.iter_mut().enumerate() {
+            star.bright &= [c, d].contains(&idx);
+        }
+
+        let (x, y) = (game.stars[c].x, game.stars[c].y);
+        let (dx, dy) = (game.stars[d].x - x, game.stars[d].y - y);
+        let direction = match (dx.abs() > dy.abs(), dx > 0, dy > 0) {
+            (true, true, _) => Direction::Right,
+            (true, false, _) => Direction::Left,
+            (false, _, true) => Direction::Down,
+            (false, _, false) => Direction::Up,
+        };
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        game.handle_input(InputEvent::Jump(direction));
+        assert_eq!(game.cursor_star(), Some(c));
+    }
+
+    #[test]
+    fn relaxed_rules_allow_crossing_links() {
+        let params = SkyParams {
+            link_rules: LinkRules::RELAXED,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params);
+        let ((a, b), (c, d)) = find_crossing_pairs(&game);
+
+        drag(&mut game, a, b);
+        assert!(drag(&mut game, c, d).contains(&GameEvent::LinkAdded {
+            start_idx: c as u16,
+            end_idx: d as u16
+        }));
+    }
+
+    #[test]
+    fn duplicate_links_are_rejected() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let link = game
+            .links
+            .iter()
+            .find(|link| {
+                game.stars[link.start_idx as usize].bright
+                    && game.stars[link.end_idx as usize].bright
+            })
+            .unwrap()
+            .clone();
+
+        let events = drag(&mut game, link.end_idx as usize, link.start_idx as usize);
+        assert!(matches!(
+            events.as_slice(),
+            [
+                GameEvent::DraftStarted { .. },
+                GameEvent::LinkRejected {
+                    reason: LinkRejection::Duplicate { .. }
+                }
+            ]
+        ));
+    }
+
+    /// Measure how long a link takes, including the check whether the round
+    /// is lost, while following the plans of the solver. Run with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_outcome() {
+        for params in [SkyParams::STANDARD, SkyParams::HUGE] {
+            let mut game = Game::new(123456, params);
+            let star_count = game.stars.len();
+
+            let start = Instant::now();
+            game.outcome();
+            let first_time = start.elapsed();
+
+            let (mut link_count, mut link_time, mut slowest) = (0, Duration::ZERO, Duration::ZERO);
+            'game: while game.outcome().is_none() {
+                let Some(plan) = game.find_completable_constellation() else {
+                    break;
+                };
+                for (link_idx, (start_idx, end_idx)) in plan.into_iter().enumerate() {
+                    let time = Instant::now();
+                    let events = drag(&mut game, start_idx as usize, end_idx as usize);
+                    let time = time.elapsed();
+                    if !events
+                        .iter()
+                        .any(|event| matches!(event, GameEvent::LinkAdded { .. }))
+                    {
+                        // The links of a plan are not checked against each other.
+                        assert!(link_idx > 0, "The first link of a plan is valid");
+                        continue 'game;
+                    }
+                    link_count += 1;
+                    link_time += time;
+                    slowest = slowest.max(time);
+                }
+            }
+
+            println!(
+                "{star_count} stars: first check {first_time:?}, {link_count} links: {:?} per link, slowest {slowest:?}",
+                link_time / link_count.max(1)
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+/// A link added by the player, together with all of its side effects,
+/// so that it can be reverted exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct LinkAction {
+    pub start_idx: u16,
+    pub end_idx: u16,
+    /// Light before the link was added, i.e. before the cost was
+    /// paid and before any refund for a completed constellation.
+    pub light_before: u8,
+    /// Size of the constellation completed by the link, or 0.
+    pub completed_size: u16,
+    /// Stars that were dimmed, either because their constellation was
+    /// locked in or because they could no longer be part of one.
+    pub dimmed_stars: Vec<u16>,
+    /// Links whose style changed from `STYLE_BRIGHT` to `STYLE_DIM`.
+    pub dimmed_links: Vec<u16>,
+}
+
+/// Undo and redo stacks for the links added by the player.
+pub(crate) struct History {
+    done: Vec<LinkAction>,
+    undone: Vec<LinkAction>,
+}
+
+impl History {
+    pub(crate) fn new() -> History {
+        History {
+            done: Vec::new(),
+            undone: Vec::new(),
+        }
+    }
+
+    /// Record a new action. This discards everything that could be redone.
+    pub(crate) fn push(&mut self, action: LinkAction) {
+        self.done.push(action);
+        self.undone.clear();
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<LinkAction> {
+        self.done.pop()
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<LinkAction> {
+        self.undone.pop()
+    }
+
+    /// Put an action back onto the redo stack, after it was undone.
+    pub(crate) fn push_undone(&mut self, action: LinkAction) {
+        self.undone.push(action);
+    }
+
+    /// Put an action back onto the undo stack, after it was redone.
+    pub(crate) fn push_redone(&mut self, action: LinkAction) {
+        self.done.push(action);
+    }
+}
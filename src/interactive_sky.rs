@@ -1,217 +1,482 @@
 #[skylite_proc::node_definition("./project/project.scm", "interactive-sky")]
 mod isky {
     use skylite_core::{ProjectControls, RenderControls};
-    use wasm4_target::{MOUSE_X, MOUSE_Y};
+    use wasm4_target::{
+        BUTTON_1, BUTTON_2, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT, BUTTON_UP, GAMEPAD1,
+        MOUSE_BUTTONS, MOUSE_LEFT, MOUSE_MIDDLE, MOUSE_RIGHT, MOUSE_X, MOUSE_Y,
+    };
 
     use super::cursor::Cursor;
     use super::hud::Hud;
+    use super::results::Results;
     use crate::Aoc;
-    use crate::line::{DraftLine, Link, STYLE_BRIGHT, STYLE_DASHED, STYLE_DIM, draw_line};
+    use crate::framebuffer::{View, Wasm4Framebuffer};
+    use crate::game::{Direction, Game, GameEvent, InputEvent, Outcome};
+    use crate::music::{Music, voice_count};
+    use crate::render::{
+        CANCEL_ANIMATION_FRAMES, Completion, render_cancelled_draft, render_completion,
+        render_draft_line,
+    };
+    use crate::save::{load_from_disk, save_to_disk};
+    use crate::settings::Settings;
     use crate::sky::Sky;
-    use crate::util::{CONSTELLATION_THRESHOLD, dim_lonely_stars, generate_sky, get_constellation};
+    use crate::sound::{Effect, SoundPlayer};
+
+    /// A draft line that was cancelled and is still retracting.
+    struct CancelledDraft {
+        start_idx: u16,
+        end_x: i16,
+        end_y: i16,
+        frame: u32,
+    }
+
+    /// The right or middle mouse button, held down on the sky.
+    struct Pan {
+        button: u8,
+        start: (i16, i16),
+        /// Whether the pointer moved far enough to drag the sky. Otherwise,
+        /// releasing the button counts as a click.
+        dragging: bool,
+    }
+
+    /// Distance in pixels that the mouse has to move before a click becomes a drag.
+    const PAN_THRESHOLD: i16 = 3;
+
+    /// Seed of the sky behind the title screen when there is no round to resume.
+    const DEMO_SEED: u32 = 123456;
 
     pub(crate) struct InteractiveSky {
         #[skylite_proc::node]
         sky: Sky,
-        sections: Vec<Vec<u16>>,
-        prev_mouse_down: bool,
-        #[skylite_proc::node]
-        draft_line: DraftLine,
+        /// Whether the sky reacts to input. It is inactive while a menu is open.
+        pub active: bool,
+        /// Whether there is a round in progress that can be continued.
+        pub resumable: bool,
+        prev_mouse_buttons: u8,
+        prev_mouse_pos: (i16, i16),
+        prev_gamepad: u8,
+        pan: Option<Pan>,
+        cancelled_draft: Option<CancelledDraft>,
+        completion: Option<Completion>,
+        sound: SoundPlayer,
+        music: Music,
+        /// Frame counter that drives the music.
+        music_frame: u32,
+        /// The star below the pointer in the previous frame.
+        prev_cursor_star: Option<usize>,
         #[skylite_proc::node]
         cursor: Cursor,
         #[skylite_proc::node]
         hud: Hud,
+        #[skylite_proc::node]
+        results: Results,
     }
 
     impl InteractiveSky {
         #[skylite_proc::new]
-        pub(crate) fn new(seed: u32) -> InteractiveSky {
-            let (sections, stars, links) = generate_sky(seed);
+        pub(crate) fn new() -> InteractiveSky {
+            // Finished rounds are not resumed, the title shows the demo sky instead.
+            let saved = load_from_disk();
+            let settings = saved
+                .as_ref()
+                .map_or(Settings::DEFAULT, |(_, settings)| *settings);
+            let game = saved
+                .map(|(game, _)| game)
+                .filter(|game| game.results().is_none());
+            let resumable = game.is_some();
+            let mut sky = match game {
+                Some(game) => Sky::with_game(game),
+                None => Sky::new(DEMO_SEED),
+            };
+            sky.settings = settings;
+            let light = sky.game.light;
+            let music = Music::new(sky.game.seed);
 
             InteractiveSky {
-                sky: Sky::new(stars, links),
-                sections,
-                prev_mouse_down: false,
-                draft_line: DraftLine::new(),
+                sky,
+                active: false,
+                resumable,
+                prev_mouse_buttons: 0,
+                prev_mouse_pos: (0, 0),
+                prev_gamepad: 0,
+                pan: None,
+                cancelled_draft: None,
+                completion: None,
+                sound: SoundPlayer::new(),
+                music,
+                music_frame: 0,
+                prev_cursor_star: None,
                 cursor: Cursor::new(),
-                hud: Hud::new(8),
+                hud: Hud::new(light),
+                results: Results::new(),
             }
         }
 
-        fn get_bright_star_idx_at(&self, x: i16, y: i16) -> Option<usize> {
-            for (idx, star) in self.sky.stars.iter().enumerate() {
-                if !star.bright {
-                    continue;
-                }
-
-                let dx = star.x - x;
-                let dy = star.y - y;
+        /// Start a new round with the sky for `seed`.
+        pub(crate) fn start(&mut self, seed: u32) {
+            let settings = self.sky.settings;
+            self.sky = Sky::new(seed);
+            self.sky.settings = settings;
+            self.resumable = true;
+            self.results.results = None;
+            self.cancelled_draft = None;
+            self.completion = None;
+            self.music = Music::new(seed);
+            self.music_frame = 0;
+            save_to_disk(&self.sky.game, &self.sky.settings);
+        }
 
-                if dx.abs() < 4 && dy.abs() < 4 {
-                    return Some(idx);
-                }
-            }
-            None
+        pub(crate) fn seed(&self) -> u32 {
+            self.sky.game.seed
         }
 
-        fn get_effective_mouse_pos(&self, focus_x: i32, focus_y: i32) -> (i16, i16) {
-            if self.draft_line.visible {
-                (self.draft_line.end_x, self.draft_line.end_y)
-            } else {
-                unsafe { (*MOUSE_X + focus_x as i16, *MOUSE_Y + focus_y as i16) }
-            }
+        pub(crate) fn game(&self) -> &Game {
+            &self.sky.game
         }
 
-        fn update_cursor(&mut self, focus_x: i32, focus_y: i32) {
-            let (effective_mouse_x, effective_mouse_y) =
-                self.get_effective_mouse_pos(focus_x, focus_y);
+        /// Move the pointer to a position in the sky, as if the mouse was moved there.
+        pub(crate) fn move_pointer_to(&mut self, x: i32, y: i32) {
+            let input = InputEvent::MoveTo {
+                x: x as i16,
+                y: y as i16,
+            };
+            self.sky.game.handle_input(input);
+        }
 
-            let mut visible = false;
-            let mut cursor_x = 0;
-            let mut cursor_y = 0;
+        /// Move the camera smoothly to the given focus.
+        pub(crate) fn ease_camera_to(&mut self, focus_x: i32, focus_y: i32) {
+            self.sky.camera.ease_to(focus_x, focus_y);
+        }
 
-            if let Some(idx) = self.get_bright_star_idx_at(effective_mouse_x, effective_mouse_y) {
-                if self.sky.stars[idx].bright {
-                    cursor_x = self.sky.stars[idx].x;
-                    cursor_y = self.sky.stars[idx].y;
-                    visible = true;
-                }
-            }
+        pub(crate) fn is_over(&self) -> bool {
+            self.results.results.is_some()
+        }
 
-            self.cursor.x = cursor_x;
-            self.cursor.y = cursor_y;
-            self.cursor.visible = visible;
+        pub(crate) fn settings(&self) -> &Settings {
+            &self.sky.settings
         }
 
-        fn draft_line_limit_len(&mut self) {
-            let start_idx = self.draft_line.start_idx as usize;
-            let start_star = &self.sky.stars[start_idx];
-            let start_x = start_star.x;
-            let start_y = start_star.y;
+        /// Change the settings with `f` and save them.
+        pub(crate) fn update_settings(&mut self, f: impl FnOnce(&mut Settings)) {
+            f(&mut self.sky.settings);
+            save_to_disk(&self.sky.game, &self.sky.settings);
+        }
 
-            let dx = self.draft_line.end_x as i32 - start_x as i32;
-            let dy = self.draft_line.end_y as i32 - start_y as i32;
-            let dist = ((dx * dx + dy * dy) as f32).sqrt();
+        pub(crate) fn set_results_visible(&mut self, visible: bool) {
+            self.results.visible = visible;
+        }
 
-            if dist > crate::util::STAR_DIST_MAX_FOR_LINE as f32 {
-                let scale = crate::util::STAR_DIST_MAX_FOR_LINE as f32 / dist;
-                self.draft_line.end_x = start_x + (dx as f32 * scale) as i16;
-                self.draft_line.end_y = start_y + (dy as f32 * scale) as i16;
+        fn play(&mut self, effect: Effect) {
+            if self.sky.settings.volume > 0 {
+                self.sound.play(effect);
             }
         }
 
-        fn add_link(&mut self, start_idx: usize, end_idx: usize) {
-            let link = Link::new(start_idx as u16, end_idx as u16, STYLE_BRIGHT);
-            self.sky.links.push(link);
-            self.hud.light -= 1;
+        /// Drag the camera with the right or middle mouse button. Returns the
+        /// button if it was released without dragging, i.e. clicked.
+        fn update_pan(
+            &mut self,
+            mouse_pos: (i16, i16),
+            mouse_pressed: u8,
+            mouse_released: u8,
+        ) -> Option<u8> {
+            if self.pan.is_none() {
+                let button = [MOUSE_RIGHT, MOUSE_MIDDLE]
+                    .into_iter()
+                    .find(|button| mouse_pressed & button != 0)?;
+                self.pan = Some(Pan {
+                    button,
+                    start: mouse_pos,
+                    dragging: false,
+                });
+            }
+            let pan = self.pan.as_mut().unwrap();
+
+            let from = if pan.dragging {
+                Some(self.prev_mouse_pos)
+            } else if (mouse_pos.0 - pan.start.0).abs() > PAN_THRESHOLD
+                || (mouse_pos.1 - pan.start.1).abs() > PAN_THRESHOLD
+            {
+                // Catch up with the movement below the threshold.
+                pan.dragging = true;
+                Some(pan.start)
+            } else {
+                None
+            };
+            if let Some(from) = from {
+                // The sky moves with the mouse, so the camera moves the other way.
+                let dx = (from.0 - mouse_pos.0) as f32;
+                let dy = (from.1 - mouse_pos.1) as f32;
+                self.sky.camera.drag_by(dx, dy);
+            }
 
-            let constellation = get_constellation(&self.sky.links, end_idx);
-            if constellation.0.len() >= CONSTELLATION_THRESHOLD {
-                self.hud.light += (constellation.0.len() - 4) as u8;
+            if mouse_released & pan.button == 0 {
+                return None;
+            }
+            let clicked = !pan.dragging;
+            let button = pan.button;
+            self.pan = None;
+            self.sky.camera.end_drag();
+            clicked.then_some(button)
+        }
 
-                for star_idx in constellation.0 {
-                    self.sky.stars[star_idx as usize].bright = false;
+        /// Translate the WASM-4 mouse and gamepad state into game input.
+        ///
+        /// The left mouse button drafts lines. Clicking the right mouse button
+        /// cancels the current draft or, if there is none, undoes the last
+        /// link. Clicking the middle button redoes it. Both buttons drag the
+        /// sky when the mouse moves while they are held.
+        ///
+        /// On the gamepad, the D-pad jumps between stars, button 1 starts
+        /// and commits a line and button 2 cancels it. Links are undone and
+        /// redone by holding button 2 and pressing left or right.
+        ///
+        /// Whichever device was used last controls the pointer, the camera
+        /// follows the pointer while the gamepad is in use.
+        fn read_input(&mut self, focus_x: i32, focus_y: i32) -> Vec<InputEvent> {
+            let mouse_pos = unsafe { (*MOUSE_X, *MOUSE_Y) };
+            let mouse_buttons = unsafe { *MOUSE_BUTTONS };
+            let gamepad = unsafe { *GAMEPAD1 };
+            let mouse_pressed = mouse_buttons & !self.prev_mouse_buttons;
+            let mouse_released = !mouse_buttons & self.prev_mouse_buttons;
+            let gamepad_pressed = gamepad & !self.prev_gamepad;
+
+            if mouse_pos != self.prev_mouse_pos || mouse_pressed != 0 {
+                self.sky.follow_pointer = false;
+            } else if gamepad_pressed != 0 {
+                self.sky.follow_pointer = true;
+            }
+            let clicked = if self.active && !self.is_over() {
+                self.update_pan(mouse_pos, mouse_pressed, mouse_released)
+            } else {
+                if self.pan.take().is_some() {
+                    self.sky.camera.end_drag();
                 }
+                None
+            };
+            self.prev_mouse_buttons = mouse_buttons;
+            self.prev_mouse_pos = mouse_pos;
+            self.prev_gamepad = gamepad;
+
+            let game = &self.sky.game;
+            let mut input = Vec::new();
+            if !self.sky.follow_pointer {
+                input.push(InputEvent::MoveTo {
+                    x: mouse_pos.0 + focus_x as i16,
+                    y: mouse_pos.1 + focus_y as i16,
+                });
+            }
+            if mouse_pressed & MOUSE_LEFT != 0 {
+                input.push(InputEvent::Press);
+            } else if mouse_released & MOUSE_LEFT != 0 {
+                input.push(InputEvent::Release);
+            }
+            if clicked == Some(MOUSE_RIGHT) {
+                input.push(if game.draft.is_some() {
+                    InputEvent::Cancel
+                } else {
+                    InputEvent::Undo
+                });
+            } else if clicked == Some(MOUSE_MIDDLE) {
+                input.push(InputEvent::Redo);
+            }
 
-                for link_idx in constellation.1 {
-                    self.sky.links[link_idx as usize].style = STYLE_DIM;
+            if gamepad & BUTTON_2 != 0 {
+                if gamepad_pressed & BUTTON_2 != 0 {
+                    input.push(InputEvent::Cancel);
+                }
+                if gamepad_pressed & BUTTON_LEFT != 0 {
+                    input.push(InputEvent::Undo);
+                }
+                if gamepad_pressed & BUTTON_RIGHT != 0 {
+                    input.push(InputEvent::Redo);
                 }
+                return input;
+            }
 
-                dim_lonely_stars(&self.sections, &mut self.sky.stars, &mut self.sky.links);
+            for (button, direction) in [
+                (BUTTON_LEFT, Direction::Left),
+                (BUTTON_RIGHT, Direction::Right),
+                (BUTTON_UP, Direction::Up),
+                (BUTTON_DOWN, Direction::Down),
+            ] {
+                if gamepad_pressed & button == 0 {
+                    continue;
+                }
+                if game.cursor_star().is_none() {
+                    // Start searching from the center of the screen.
+                    let half_screen = wasm4_target::SCREEN_SIZE as i32 / 2;
+                    input.push(InputEvent::MoveTo {
+                        x: (focus_x + half_screen) as i16,
+                        y: (focus_y + half_screen) as i16,
+                    });
+                }
+                input.push(InputEvent::Jump(direction));
+            }
+            if gamepad_pressed & BUTTON_1 != 0 {
+                input.push(if game.draft.is_none() {
+                    InputEvent::Press
+                } else {
+                    InputEvent::Release
+                });
             }
+            input
         }
 
-        fn update_mouse_state(&mut self, focus_x: i32, focus_y: i32) {
-            if self.draft_line.visible {
-                let (mouse_x, mouse_y) =
-                    unsafe { (*MOUSE_X + focus_x as i16, *MOUSE_Y + focus_y as i16) };
-                let draft_line = &mut self.draft_line;
-                draft_line.end_x = mouse_x;
-                draft_line.end_y = mouse_y;
-                self.draft_line_limit_len();
+        fn update_input(&mut self, focus_x: i32, focus_y: i32) {
+            let input = self.read_input(focus_x, focus_y);
+            if self.completion.is_some() && self.sky.settings.block_input_during_effects {
+                return;
+            }
+            let game = &mut self.sky.game;
+            let mut events = Vec::new();
+            for input in input {
+                events.extend(game.handle_input(input));
             }
 
-            let (effective_mouse_x, effective_mouse_y) =
-                self.get_effective_mouse_pos(focus_x, focus_y);
-            let mouse_down =
-                unsafe { (*wasm4_target::MOUSE_BUTTONS) & wasm4_target::MOUSE_LEFT != 0 };
-
-            let star_idx_opt = self.get_bright_star_idx_at(effective_mouse_x, effective_mouse_y);
-
-            if !self.prev_mouse_down && mouse_down {
-                if let Some(star_idx) = star_idx_opt {
-                    self.draft_line.start_idx = star_idx as u16;
-                    self.draft_line.end_x = effective_mouse_x;
-                    self.draft_line.end_y = effective_mouse_y;
-                    self.draft_line.visible = true;
-                }
-            } else if self.prev_mouse_down && !mouse_down {
-                if let Some(end_idx) = star_idx_opt {
-                    let start_idx = self.draft_line.start_idx as usize;
-                    if start_idx != end_idx {
-                        self.add_link(start_idx, end_idx);
+            if events.iter().any(|event| {
+                matches!(
+                    event,
+                    GameEvent::LinkAdded { .. } | GameEvent::LinkRemoved { .. }
+                )
+            }) {
+                save_to_disk(game, &self.sky.settings);
+            }
+            for event in &events {
+                match event {
+                    GameEvent::GameOver { outcome } => {
+                        self.resumable = false;
+                        self.results.results = self.sky.game.results();
+                        if *outcome == Outcome::Lost && self.sky.game.light == 0 {
+                            self.play(Effect::OutOfLight);
+                        }
+                    }
+                    GameEvent::DraftCancelled {
+                        start_idx,
+                        end_x,
+                        end_y,
+                    } => {
+                        self.cancelled_draft = Some(CancelledDraft {
+                            start_idx: *start_idx,
+                            end_x: *end_x,
+                            end_y: *end_y,
+                            frame: 0,
+                        });
+                        self.play(Effect::DraftCancelled);
                     }
+                    GameEvent::ConstellationCompleted {
+                        star_idx, light, ..
+                    } => {
+                        self.completion = Some(Completion::new(&self.sky.game, *star_idx, *light));
+                        self.play(Effect::ConstellationCompleted);
+                    }
+                    GameEvent::LinkRemoved { .. } => self.completion = None,
+                    GameEvent::DraftStarted { .. } => self.play(Effect::DraftStarted),
+                    GameEvent::LinkAdded { .. } => self.play(Effect::LinkAdded),
+                    GameEvent::LinkRejected { .. } => self.play(Effect::LinkRejected),
+                    _ => {}
                 }
             }
+        }
 
-            if mouse_down {
-                if self.draft_line.visible {
-                    // Snap line to stars if close enough.
-                    // We do not limit the line length after this,
-                    // because there should never be a case where the snapping
-                    // would create a line that is too long. This would mean
-                    // that the generated sky contains stars whose distance
-                    // is within the dead zone defined by [STAR_DIST_MAX_FOR_LINE: STAR_DIST_DEAD_ZONE_END)
-                    if let Some(idx) = star_idx_opt {
-                        let x = self.sky.stars[idx].x;
-                        let y = self.sky.stars[idx].y;
-
-                        self.draft_line.end_x = x;
-                        self.draft_line.end_y = y;
-                    }
-                }
+        fn update_cursor(&mut self) {
+            let game = &self.sky.game;
+            let cursor_star = game.cursor_star();
+            if let Some(idx) = cursor_star {
+                self.cursor.x = game.stars[idx].x;
+                self.cursor.y = game.stars[idx].y;
+                self.cursor.visible = true;
             } else {
-                self.draft_line.visible = false;
+                self.cursor.visible = false;
             }
 
-            self.prev_mouse_down = mouse_down;
+            let snapped = cursor_star.is_some_and(|idx| {
+                game.draft
+                    .is_some_and(|draft| draft.start_idx as usize != idx)
+            });
+            if snapped && cursor_star != self.prev_cursor_star {
+                self.play(Effect::Snap);
+            }
+            self.prev_cursor_star = cursor_star;
         }
 
         #[skylite_proc::pre_update]
         fn pre_update(&mut self, controls: &mut ProjectControls<Aoc>) {
             let (focus_x, focus_y) = controls.get_focus();
-            self.update_mouse_state(focus_x, focus_y);
-            self.update_cursor(focus_x, focus_y);
+            self.sky.game.star_hit_radius = self.sky.settings.star_hit_radius();
+            if self.active && !self.is_over() {
+                self.update_input(focus_x, focus_y);
+            } else {
+                // Keep track of the buttons, so that the click which closes
+                // a menu does not also reach the game.
+                self.read_input(focus_x, focus_y);
+            }
+            self.sky.scrolling = self.active;
+            self.update_cursor();
+            if let Some(cancelled) = &mut self.cancelled_draft {
+                cancelled.frame += 1;
+                if cancelled.frame >= CANCEL_ANIMATION_FRAMES {
+                    self.cancelled_draft = None;
+                }
+            }
+            if let Some(completion) = &mut self.completion {
+                completion.frame += 1;
+                if completion.is_done() {
+                    self.completion = None;
+                }
+            }
+            let volume = self.sky.settings.volume;
+            self.sound.update(|tone| tone.scaled(volume).play());
+            let voices = voice_count(self.sky.game.completed_constellations);
+            self.music.update(self.music_frame, voices, |tone| {
+                if volume > 0 && !self.sound.is_playing(tone.channel) {
+                    tone.scaled(volume).play();
+                }
+            });
+            self.music_frame += 1;
+            let pending_light = self.completion.as_ref().map_or(0, |c| c.pending_light());
+            self.hud.light = self.sky.game.light.saturating_sub(pending_light);
             controls.set_focus(focus_x, focus_y);
         }
 
         #[skylite_proc::render]
         fn render(&self, ctx: &mut RenderControls<Aoc>) {
-            if self.draft_line.visible {
-                let line = &self.draft_line;
-                let start = &self.sky.stars[line.start_idx as usize];
-                draw_line(start.x, start.y, line.end_x, line.end_y, STYLE_DASHED, ctx);
+            let view = View {
+                reduce_motion: self.sky.settings.accessible,
+                ..View::from_controls(ctx)
+            };
+            render_draft_line(&self.sky.game, &view, &mut Wasm4Framebuffer);
+            if let Some(cancelled) = &self.cancelled_draft {
+                render_cancelled_draft(
+                    &self.sky.game,
+                    cancelled.start_idx,
+                    cancelled.end_x,
+                    cancelled.end_y,
+                    cancelled.frame,
+                    &view,
+                    &mut Wasm4Framebuffer,
+                );
+            }
+            if let Some(completion) = &self.completion {
+                render_completion(
+                    &self.sky.game,
+                    completion,
+                    self.hud.light,
+                    &view,
+                    &mut Wasm4Framebuffer,
+                );
             }
         }
     }
 }
 pub(crate) use isky::*;
 
-static CURSOR_GRAPHIC: &[u8] = &[
-    0b1100_0110,
-    0b1000_0010,
-    0b0000_0000,
-    0b0000_0000,
-    0b0000_0000,
-    0b1000_0010,
-    0b1100_0110,
-];
-
 #[skylite_proc::node_definition("./project/project.scm", "cursor")]
 mod cursor {
     use crate::Aoc;
+    use crate::framebuffer::{View, Wasm4Framebuffer};
+    use crate::render::render_cursor;
     use skylite_core::RenderControls;
-    use wasm4_target::blit;
 
     pub(crate) struct Cursor {
         pub x: i16,
@@ -231,14 +496,11 @@ mod cursor {
 
         #[skylite_proc::render]
         fn render(&self, ctx: &mut RenderControls<Aoc>) {
-            let (focus_x, focus_y) = ctx.get_focus();
-            blit(
-                super::CURSOR_GRAPHIC,
-                (self.x - 3) as i32 - focus_x,
-                (self.y - 3) as i32 - focus_y,
-                8,
-                7,
-                wasm4_target::BLIT_1BPP,
+            render_cursor(
+                self.x,
+                self.y,
+                &View::from_controls(ctx),
+                &mut Wasm4Framebuffer,
             );
         }
 
@@ -252,23 +514,9 @@ mod cursor {
 #[skylite_proc::node_definition("./project/project.scm", "hud")]
 mod hud {
     use crate::Aoc;
+    use crate::framebuffer::Wasm4Framebuffer;
+    use crate::render::render_hud;
     use skylite_core::RenderControls;
-    use skylite_core::SkyliteTarget;
-    use wasm4_target::SCREEN_SIZE;
-
-    static LIGHT: &[u8] = &[
-        0b00_01_10_10,
-        0b01_00_01_10,
-        0b11_11_10_01,
-        0b10_11_11_11,
-        0b11_10_10_11,
-        0b11_11_11_10,
-        0b01_10_11_11,
-        0b10_01_00_01,
-        0b10_10_01_00,
-        6,
-        0,
-    ];
 
     pub(crate) struct Hud {
         pub light: u8,
@@ -281,21 +529,8 @@ mod hud {
         }
 
         #[skylite_proc::render]
-        fn render(&self, ctx: &mut RenderControls<Aoc>) {
-            for i in 0..self.light {
-                ctx.get_target_instance_mut().draw_sub(
-                    LIGHT,
-                    (2 + i * 8) as i16,
-                    (SCREEN_SIZE - 8) as i16,
-                    0,
-                    0,
-                    6,
-                    6,
-                    false,
-                    false,
-                    false,
-                );
-            }
+        fn render(&self, _ctx: &mut RenderControls<Aoc>) {
+            render_hud(self.light, &mut Wasm4Framebuffer);
         }
 
         #[skylite_proc::z_order]
@@ -304,3 +539,45 @@ mod hud {
         }
     }
 }
+
+#[skylite_proc::node_definition("./project/project.scm", "results")]
+mod results {
+    use crate::Aoc;
+    use crate::framebuffer::Wasm4Framebuffer;
+    use crate::game::GameResults;
+    use crate::render::render_results;
+    use skylite_core::RenderControls;
+
+    /// The results screen, shown on top of the sky once a round is over.
+    pub(crate) struct Results {
+        pub results: Option<GameResults>,
+        pub visible: bool,
+    }
+
+    impl Results {
+        #[skylite_proc::new]
+        pub(crate) fn new() -> Results {
+            Results {
+                results: None,
+                visible: false,
+            }
+        }
+
+        #[skylite_proc::render]
+        fn render(&self, _ctx: &mut RenderControls<Aoc>) {
+            if let Some(results) = &self.results {
+                render_results(results, &mut Wasm4Framebuffer);
+            }
+        }
+
+        #[skylite_proc::is_visible]
+        fn is_visible(&self, _ctx: &RenderControls<Aoc>) -> bool {
+            self.visible && self.results.is_some()
+        }
+
+        #[skylite_proc::z_order]
+        fn z_order(&self) -> i32 {
+            20
+        }
+    }
+}
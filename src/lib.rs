@@ -1,30 +1,63 @@
+// The game core is also compiled for the host, so that it can be tested
+// with `make test`. Anything that only the cart uses is dead code there.
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+#[cfg(target_arch = "wasm32")]
 use std::{cell::RefCell, panic::{set_hook, PanicHookInfo}};
 
+#[cfg(target_arch = "wasm32")]
 use skylite_core::SkyliteProject;
+#[cfg(target_arch = "wasm32")]
 use wasm4_target::{trace, w4alloc::W4Alloc, Wasm4Target};
 
+#[cfg(target_arch = "wasm32")]
 use crate::aoc::Aoc;
 
+mod camera;
+mod constellations;
+mod font;
+mod framebuffer;
+mod game;
+mod history;
 mod star;
-mod interactive_sky;
-mod sky;
 mod line;
+mod music;
+mod palette;
+mod render;
+mod rules;
+mod save;
+mod scene;
+mod settings;
+mod solver;
+mod sound;
+mod spatial;
 mod util;
+#[cfg(test)]
+mod validate;
+#[cfg(target_arch = "wasm32")]
+mod app;
+#[cfg(target_arch = "wasm32")]
+mod interactive_sky;
+#[cfg(target_arch = "wasm32")]
+mod sky;
 
+#[cfg(target_arch = "wasm32")]
 #[global_allocator]
 static ALLOC: W4Alloc = W4Alloc::new();
 
+#[cfg(target_arch = "wasm32")]
 #[skylite_proc::skylite_project("./project/project.scm", Wasm4Target)]
 mod aoc {
     use wasm4_target::Wasm4Target;
-    use crate::interactive_sky::InteractiveSky;
+    use crate::app::App;
 }
 
+#[cfg(target_arch = "wasm32")]
 thread_local! {
     static GAME: RefCell<Option<Aoc>> = RefCell::new(None);
 }
 
-#[cfg(debug_assertions)]
+#[cfg(all(target_arch = "wasm32", debug_assertions))]
 fn panic_hook(info: &PanicHookInfo) {
     trace(format!("Cart panicked: {:?}\n", info.payload()));
     if let Some(location) = info.location() {
@@ -32,6 +65,7 @@ fn panic_hook(info: &PanicHookInfo) {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 #[unsafe(no_mangle)]
 fn start() {
     #[cfg(debug_assertions)]
@@ -43,6 +77,7 @@ fn start() {
     });
 }
 
+#[cfg(target_arch = "wasm32")]
 #[unsafe(no_mangle)]
 fn update() {
     GAME.with(|game| {
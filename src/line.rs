@@ -1,42 +1,47 @@
 pub const STYLE_DASHED: u8 = 0;
 pub const STYLE_DIM: u8 = 1;
 pub const STYLE_BRIGHT: u8 = 2;
-use skylite_core::RenderControls;
-use wasm4_target::{FRAMEBUFFER, SCREEN_SIZE};
+/// Draft line that ends on a star it cannot be linked to.
+pub const STYLE_REJECTED: u8 = 3;
 
-use crate::Aoc;
+use crate::framebuffer::{Framebuffer, SCREEN_SIZE, View};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Link {
+    pub start_idx: u16,
+    pub end_idx: u16,
+    pub style: u8,
+}
+
+impl Link {
+    pub(crate) fn new(start_idx: u16, end_idx: u16, style: u8) -> Link {
+        Link {
+            start_idx,
+            end_idx,
+            style,
+        }
+    }
+}
 
 static LINE_END_DIM: &[u8] = &[1, 0, 1, 1, 0];
 static LINE_END_BRIGHT: &[u8] = &[1, 1, 2, 1, 2];
 
-fn get_color(style: u8, steps: u32, progress: u32, timer: u32) -> u8 {
+/// Colour of the pixel at `progress` along a line of `steps` pixels. `timer`
+/// drives the dashes and the shine, without it the line does not move.
+fn get_color(style: u8, steps: u32, progress: u32, timer: Option<u32>) -> u8 {
     match style {
-        STYLE_DASHED => {
-            if progress < 4 {
-                return 0;
-            }
-        }
-        STYLE_DIM => {
-            if progress < 3 || progress > steps - 3 {
-                return 0;
-            }
-        }
-        STYLE_BRIGHT => {
-            if progress < 4 || progress > steps - 4 {
-                return 0;
-            }
-        }
+        STYLE_DASHED | STYLE_REJECTED if progress < 4 => return 0,
+        STYLE_DIM if progress < 3 || progress > steps - 3 => return 0,
+        STYLE_BRIGHT if progress < 4 || progress > steps - 4 => return 0,
         _ => {}
     }
 
+    let dash_offset = timer.map_or(0, |timer| (timer / 6) % 5);
     match style {
-        STYLE_DASHED => {
-            if (progress - (timer / 6) % 5 + 5) % 5 < 3 {
-                1
-            } else {
-                0
-            }
-        }
+        STYLE_DASHED if (progress - dash_offset + 5) % 5 < 3 => 1,
+        STYLE_DASHED => 0,
+        STYLE_REJECTED if progress.is_multiple_of(2) => 1,
+        STYLE_REJECTED => 0,
         STYLE_DIM => {
             if progress < 8 {
                 LINE_END_DIM[(progress - 3) as usize]
@@ -47,7 +52,6 @@ fn get_color(style: u8, steps: u32, progress: u32, timer: u32) -> u8 {
             }
         }
         STYLE_BRIGHT => {
-            let shine_progress = (timer & 0xff) << 1;
             let color = if progress < 9 {
                 LINE_END_BRIGHT[(progress - 4) as usize]
             } else if steps - progress < 9 {
@@ -56,6 +60,10 @@ fn get_color(style: u8, steps: u32, progress: u32, timer: u32) -> u8 {
                 2
             };
 
+            let Some(timer) = timer else {
+                return color;
+            };
+            let shine_progress = (timer & 0xff) << 1;
             if progress >= shine_progress && progress < shine_progress + 6 {
                 color + 1
             } else {
@@ -72,83 +80,28 @@ pub(crate) fn draw_line(
     x2: i16,
     y2: i16,
     style: u8,
-    ctx: &mut RenderControls<Aoc>,
+    view: &View,
+    fb: &mut impl Framebuffer,
 ) {
-    let (focus_x, focus_y) = ctx.get_focus();
     let dx = (x2 - x1) as i32;
     let dy = (y2 - y1) as i32;
     let steps = i32::max(dx.abs(), dy.abs());
 
     for i in 0..steps {
-        let x = (x1 as i32) - focus_x + (dx * (2 * i + 1) / (2 * steps));
-        let y = (y1 as i32) - focus_y + (dy * (2 * i + 1) / (2 * steps));
+        let x = (x1 as i32) - view.focus_x + (dx * (2 * i + 1) / (2 * steps));
+        let y = (y1 as i32) - view.focus_y + (dy * (2 * i + 1) / (2 * steps));
 
         if x < 0 || x >= SCREEN_SIZE as i32 || y < 0 || y >= SCREEN_SIZE as i32 {
             continue;
         }
 
-        let color = get_color(style, steps as u32, i as u32, ctx.get_update_count());
+        let timer = (!view.reduce_motion).then_some(view.frame);
+        let color = get_color(style, steps as u32, i as u32, timer);
 
         if color == 0 {
             continue;
         }
 
-        let pixel_idx = (y * wasm4_target::SCREEN_SIZE as i32 + x) as usize;
-        let byte = pixel_idx >> 2;
-        let shift = (pixel_idx & 0b11) * 2;
-
-        unsafe {
-            let frame_buffer = &mut *FRAMEBUFFER;
-            frame_buffer[byte] &= !(0b11 << shift);
-            frame_buffer[byte] |= color << shift;
-        }
-    }
-}
-
-#[skylite_proc::node_definition("./project/project.scm", "link")]
-mod link {
-    use crate::Aoc;
-
-    pub(crate) struct Link {
-        pub start_idx: u16,
-        pub end_idx: u16,
-        pub style: u8,
-    }
-
-    impl Link {
-        #[skylite_proc::new]
-        pub(crate) fn new(start_idx: u16, end_idx: u16, style: u8) -> Link {
-            Link {
-                start_idx,
-                end_idx,
-                style,
-            }
-        }
-    }
-}
-pub(crate) use link::*;
-
-#[skylite_proc::node_definition("./project/project.scm", "draft-line")]
-mod draft_line {
-    use crate::Aoc;
-
-    pub(crate) struct DraftLine {
-        pub start_idx: u16,
-        pub end_x: i16,
-        pub end_y: i16,
-        pub visible: bool,
-    }
-
-    impl DraftLine {
-        #[skylite_proc::new]
-        pub(crate) fn new() -> DraftLine {
-            DraftLine {
-                start_idx: 0,
-                end_x: 0,
-                end_y: 0,
-                visible: false,
-            }
-        }
+        fb.set_pixel(x, y, color);
     }
 }
-pub(crate) use draft_line::*;
@@ -0,0 +1,229 @@
+use crate::sound::{CHANNEL_PULSE1, CHANNEL_PULSE2, CHANNEL_TRIANGLE, Tone};
+use crate::util::next_random;
+
+/// Number of frames per beat.
+const BEAT_FRAMES: u32 = 30;
+
+/// Scale degree that marks a rest.
+const REST: u8 = 0xf;
+
+// Patterns are lists of notes, one byte per note. The upper four bits are
+// the degree in `SCALE` (or `REST`), the lower four bits the duration in
+// beats. Every pattern is 8 beats long, so that the voices stay in step.
+
+const fn note(degree: u8, beats: u8) -> u8 {
+    (degree << 4) | beats
+}
+
+const fn rest(beats: u8) -> u8 {
+    note(REST, beats)
+}
+
+const BASS_PATTERNS: &[&[u8]] = &[
+    &[note(0, 4), note(3, 4)],
+    &[note(0, 4), note(2, 2), note(1, 2)],
+    &[note(3, 4), note(2, 4)],
+    &[note(0, 6), rest(2)],
+];
+
+const MELODY_PATTERNS: &[&[u8]] = &[
+    &[note(4, 2), note(5, 1), note(6, 1), note(5, 4)],
+    &[rest(2), note(7, 2), note(6, 2), note(4, 2)],
+    &[note(5, 3), note(3, 1), note(4, 4)],
+    &[note(6, 1), note(5, 1), note(4, 2), rest(4)],
+    &[note(2, 2), note(4, 2), note(5, 2), note(7, 2)],
+];
+
+const COUNTER_PATTERNS: &[&[u8]] = &[
+    &[rest(4), note(7, 2), note(9, 2)],
+    &[note(8, 4), rest(4)],
+    &[rest(2), note(5, 1), note(7, 1), note(6, 4)],
+    &[rest(8)],
+];
+
+/// Minor pentatonic scale, as semitones above the root.
+const SCALE: [u8; 10] = [0, 3, 5, 7, 10, 12, 15, 17, 19, 22];
+
+/// Frequencies of the semitones from A2 upwards, in Hz.
+const SEMITONE_FREQS: [u16; 52] = [
+    110, 117, 123, 131, 139, 147, 156, 165, 175, 185, 196, 208, 220, 233, 247, 262, 277, 294, 311,
+    330, 349, 370, 392, 415, 440, 466, 494, 523, 554, 587, 622, 659, 698, 740, 784, 831, 880, 932,
+    988, 1047, 1109, 1175, 1245, 1319, 1397, 1480, 1568, 1661, 1760, 1865, 1976, 2093,
+];
+
+/// One line of the music, playing patterns on a single channel.
+struct Voice {
+    patterns: &'static [&'static [u8]],
+    channel: u8,
+    /// Semitones above the root of the song.
+    pitch: u8,
+    volume: u8,
+    pattern: &'static [u8],
+    position: usize,
+    next_frame: u32,
+    rng: u32,
+}
+
+impl Voice {
+    fn new(
+        patterns: &'static [&'static [u8]],
+        channel: u8,
+        pitch: u8,
+        volume: u8,
+        rng: u32,
+    ) -> Voice {
+        Voice {
+            patterns,
+            channel,
+            pitch,
+            volume,
+            pattern: &[],
+            position: 0,
+            next_frame: 0,
+            rng,
+        }
+    }
+
+    /// The note starting at `frame`, if any.
+    fn update(&mut self, frame: u32, root: u8) -> Option<Tone> {
+        if frame < self.next_frame {
+            return None;
+        }
+        if self.position >= self.pattern.len() {
+            self.pattern = self.patterns[next_random(&mut self.rng) as usize % self.patterns.len()];
+            self.position = 0;
+        }
+
+        let byte = self.pattern[self.position];
+        self.position += 1;
+        let (degree, beats) = (byte >> 4, (byte & 0xf) as u32);
+        self.next_frame = frame + beats * BEAT_FRAMES;
+        if degree == REST {
+            return None;
+        }
+
+        let freq = SEMITONE_FREQS[(root + self.pitch + SCALE[degree as usize]) as usize];
+        let length = beats * BEAT_FRAMES;
+        Some(Tone {
+            freq_start: freq,
+            freq_end: freq,
+            sustain: (length / 2) as u8,
+            release: (length / 2) as u8,
+            volume: self.volume,
+            channel: self.channel,
+        })
+    }
+}
+
+/// Background music, generated from the seed of the sky.
+pub(crate) struct Music {
+    voices: [Voice; 3],
+    /// Transposition of the whole song, in semitones.
+    root: u8,
+}
+
+impl Music {
+    pub(crate) fn new(seed: u32) -> Music {
+        let mut rng = seed;
+        let root = (next_random(&mut rng) % 5) as u8;
+        Music {
+            voices: [
+                Voice::new(
+                    BASS_PATTERNS,
+                    CHANNEL_TRIANGLE,
+                    0,
+                    12,
+                    next_random(&mut rng),
+                ),
+                Voice::new(
+                    MELODY_PATTERNS,
+                    CHANNEL_PULSE1,
+                    12,
+                    6,
+                    next_random(&mut rng),
+                ),
+                Voice::new(
+                    COUNTER_PATTERNS,
+                    CHANNEL_PULSE2,
+                    12,
+                    4,
+                    next_random(&mut rng),
+                ),
+            ],
+            root,
+        }
+    }
+
+    /// Pass the notes that start at `frame` to `output`. This has to be called
+    /// for every frame. Voices beyond `voice_count` keep time, but stay silent.
+    pub(crate) fn update(&mut self, frame: u32, voice_count: usize, mut output: impl FnMut(&Tone)) {
+        for (idx, voice) in self.voices.iter_mut().enumerate() {
+            if let Some(tone) = voice.update(frame, self.root)
+                && idx < voice_count
+            {
+                output(&tone);
+            }
+        }
+    }
+}
+
+/// Number of voices to play, a new one joins with each of the first completed constellations.
+pub(crate) fn voice_count(completed_constellations: u16) -> usize {
+    1 + completed_constellations.min(2) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The notes played during the first `frames` frames, with the frame they start at.
+    fn dump(seed: u32, frames: u32, voice_count: usize) -> Vec<(u32, Tone)> {
+        let mut music = Music::new(seed);
+        let mut out = Vec::new();
+        for frame in 0..frames {
+            music.update(frame, voice_count, |tone| out.push((frame, *tone)));
+        }
+        out
+    }
+
+    #[test]
+    fn music_depends_on_seed() {
+        assert_eq!(dump(123456, 3600, 3), dump(123456, 3600, 3));
+        assert_ne!(dump(123456, 3600, 3), dump(654321, 3600, 3));
+    }
+
+    #[test]
+    fn notes_start_on_beats() {
+        let notes = dump(123456, 3600, 3);
+        assert!(!notes.is_empty());
+        assert!(notes.iter().all(|(frame, _)| frame % BEAT_FRAMES == 0));
+    }
+
+    #[test]
+    fn completed_constellations_add_voices() {
+        let channels = |voice_count| {
+            let mut channels: Vec<u8> = dump(1, 3600, voice_count)
+                .iter()
+                .map(|(_, tone)| tone.channel)
+                .collect();
+            channels.sort();
+            channels.dedup();
+            channels
+        };
+        assert_eq!(channels(voice_count(0)), vec![CHANNEL_TRIANGLE]);
+        assert_eq!(
+            channels(voice_count(1)),
+            vec![CHANNEL_PULSE1, CHANNEL_TRIANGLE]
+        );
+        assert_eq!(channels(voice_count(5)).len(), 3);
+
+        // Silent voices still keep time, so the bass does not change.
+        let bass = |voice_count| {
+            dump(1, 3600, voice_count)
+                .into_iter()
+                .filter(|(_, tone)| tone.channel == CHANNEL_TRIANGLE)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bass(1), bass(3));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::util::next_random;
+
+/// The four colours of the WASM-4 palette. Colour 0 is the background,
+/// colour 3 is used for the brightest stars and lines.
+pub(crate) type Palette = [u32; 4];
+
+/// Named colour themes for the sky.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Theme {
+    Night,
+    Dawn,
+    Aurora,
+    HighContrast,
+    /// Dark ink on a light background, for e-ink displays.
+    EInk,
+    /// Blue and orange, which can be told apart with all common forms of colour blindness.
+    ColorBlindSafe,
+}
+
+impl Theme {
+    pub(crate) const ALL: [Theme; 6] = [
+        Theme::Night,
+        Theme::Dawn,
+        Theme::Aurora,
+        Theme::HighContrast,
+        Theme::EInk,
+        Theme::ColorBlindSafe,
+    ];
+
+    /// The themes that a sky can get from its seed.
+    const SKY_THEMES: [Theme; 3] = [Theme::Night, Theme::Dawn, Theme::Aurora];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Theme::Night => "NIGHT",
+            Theme::Dawn => "DAWN",
+            Theme::Aurora => "AURORA",
+            Theme::HighContrast => "HIGH CONTRAST",
+            Theme::EInk => "E-INK",
+            Theme::ColorBlindSafe => "COLOUR SAFE",
+        }
+    }
+
+    pub(crate) fn palette(self) -> Palette {
+        match self {
+            Theme::Night => [0x040411, 0x32324B, 0x697B9E, 0xFAFAF0],
+            Theme::Dawn => [0x2B1B3D, 0x7A4A6B, 0xE29A7A, 0xFFF1D6],
+            Theme::Aurora => [0x021418, 0x1D4F4A, 0x4FC08D, 0xE8FFF4],
+            Theme::HighContrast => [0x000000, 0x0050C0, 0xFFD000, 0xFFFFFF],
+            Theme::EInk => [0xF2F0E6, 0xB8B6AE, 0x5E5D59, 0x111111],
+            Theme::ColorBlindSafe => [0x000000, 0x0072B2, 0xE69F00, 0xF0F0F0],
+        }
+    }
+
+    /// The theme of the sky for `seed`, used when the player did not choose one.
+    pub(crate) fn from_seed(seed: u32) -> Theme {
+        let mut rng = seed.rotate_left(8);
+        Theme::SKY_THEMES[next_random(&mut rng) as usize % Theme::SKY_THEMES.len()]
+    }
+}
+
+/// Mix two colours, `weight` is the share of `b` in 256ths.
+fn mix(a: u32, b: u32, weight: u32) -> u32 {
+    let channel = |shift: u32| {
+        let (a, b) = ((a >> shift) & 0xff, (b >> shift) & 0xff);
+        ((a * (256 - weight) + b * weight) / 256) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+/// Replace the middle colours of a palette, which are used for dim stars and
+/// lines, by colours that are evenly spaced between the background and colour 3.
+pub(crate) fn high_contrast(palette: Palette) -> Palette {
+    let [background, _, _, foreground] = palette;
+    [
+        background,
+        mix(background, foreground, 96),
+        mix(background, foreground, 176),
+        foreground,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::{MemoryFramebuffer, View};
+    use crate::game::Game;
+    use crate::render::render_game;
+    use crate::settings::Settings;
+    use crate::util::SkyParams;
+
+    /// Perceived brightness of a colour, from 0 to 255.
+    fn luminance(color: u32) -> u32 {
+        let (r, g, b) = ((color >> 16) & 0xff, (color >> 8) & 0xff, color & 0xff);
+        (r * 299 + g * 587 + b * 114) / 1000
+    }
+
+    #[test]
+    fn colors_can_be_told_apart() {
+        for theme in Theme::ALL {
+            let min_difference = if theme == Theme::HighContrast { 50 } else { 20 };
+            let palette = theme.palette();
+            for a in 0..4 {
+                for b in a + 1..4 {
+                    let difference = luminance(palette[a]).abs_diff(luminance(palette[b]));
+                    assert!(
+                        difference >= min_difference,
+                        "Colours {a} and {b} of {theme:?} are too similar"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn high_contrast_spreads_colors() {
+        for theme in Theme::ALL {
+            let palette = high_contrast(theme.palette());
+            for idx in 0..3 {
+                let difference = luminance(palette[idx]).abs_diff(luminance(palette[idx + 1]));
+                assert!(difference >= 50, "Colours of {theme:?} are too similar");
+            }
+        }
+    }
+
+    #[test]
+    fn seeds_pick_sky_themes() {
+        let themes: Vec<Theme> = (0..100).map(Theme::from_seed).collect();
+        for theme in Theme::SKY_THEMES {
+            assert!(themes.contains(&theme));
+        }
+        assert!(themes.iter().all(|theme| Theme::SKY_THEMES.contains(theme)));
+    }
+
+    /// Render the initial sky in every theme, to `target/themes/<name>.ppm`.
+    #[test]
+    #[ignore]
+    fn preview_themes() {
+        let game = Game::new(123456, SkyParams::STANDARD);
+        let view = View {
+            focus_x: 240,
+            focus_y: 240,
+            frame: 0,
+            reduce_motion: false,
+        };
+        let mut fb = MemoryFramebuffer::new();
+        render_game(&game, &Settings::DEFAULT, &view, &mut fb);
+
+        let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/themes");
+        std::fs::create_dir_all(&dir).unwrap();
+        for theme in Theme::ALL {
+            let name = theme.name().to_lowercase().replace(' ', "_");
+            let path = dir.join(format!("{name}.ppm"));
+            std::fs::write(&path, fb.to_ppm(&theme.palette())).unwrap();
+            println!("{}", path.display());
+        }
+    }
+}
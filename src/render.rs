@@ -0,0 +1,797 @@
+use crate::font::{GLYPH_HEIGHT, GLYPH_WIDTH, draw_text, draw_text_centered, text_width};
+use crate::framebuffer::{Framebuffer, SCREEN_SIZE, View};
+use crate::game::{Game, GameResults, Outcome};
+use crate::line::{STYLE_BRIGHT, STYLE_DASHED, STYLE_REJECTED, draw_line};
+use crate::scene::{Menu, SEED_DIGITS, SeedSelection, TRANSITION_FRAMES};
+use crate::settings::Settings;
+use crate::util::SkyParams;
+
+static CURSOR_GRAPHIC: &[u8] = &[
+    0b1100_0110,
+    0b1000_0010,
+    0b0000_0000,
+    0b0000_0000,
+    0b0000_0000,
+    0b1000_0010,
+    0b1100_0110,
+];
+
+static LIGHT: &[u8] = &[
+    0b00_01_10_10,
+    0b01_00_01_10,
+    0b11_11_10_01,
+    0b10_11_11_11,
+    0b11_10_10_11,
+    0b11_11_11_10,
+    0b01_10_11_11,
+    0b10_01_00_01,
+    0b10_10_01_00,
+    6,
+    0,
+];
+
+#[cfg(target_arch = "wasm32")]
+impl View {
+    pub(crate) fn from_controls(ctx: &skylite_core::RenderControls<crate::Aoc>) -> View {
+        let (focus_x, focus_y) = ctx.get_focus();
+        View {
+            focus_x,
+            focus_y,
+            frame: ctx.get_update_count(),
+            reduce_motion: false,
+        }
+    }
+}
+
+/// Draw the cursor around the star at `(x, y)`.
+pub(crate) fn render_cursor(x: i16, y: i16, view: &View, fb: &mut impl Framebuffer) {
+    fb.blit_1bpp(
+        CURSOR_GRAPHIC,
+        (x - 3) as i32 - view.focus_x,
+        (y - 3) as i32 - view.focus_y,
+        8,
+        7,
+        1,
+    );
+}
+
+/// Draw the remaining light at the bottom of the screen.
+pub(crate) fn render_hud(light: u8, fb: &mut impl Framebuffer) {
+    for i in 0..light as i32 {
+        fb.blit_2bpp(LIGHT, 2 + i * 8, SCREEN_SIZE as i32 - 8, 6, 6);
+    }
+}
+
+/// Draw an empty panel across the screen, with a border at the top and the bottom.
+fn draw_panel(top: i32, height: u32, fb: &mut impl Framebuffer) {
+    fb.fill_rect(8, top, SCREEN_SIZE as u32 - 16, height, 0);
+    fb.fill_rect(8, top, SCREEN_SIZE as u32 - 16, 1, 2);
+    fb.fill_rect(8, top + height as i32 - 1, SCREEN_SIZE as u32 - 16, 1, 2);
+}
+
+/// Draw the results screen at the end of a round.
+pub(crate) fn render_results(results: &GameResults, fb: &mut impl Framebuffer) {
+    let (top, height) = (44, 72);
+    draw_panel(top, height, fb);
+
+    let title = match results.outcome {
+        Outcome::Won => "THE SKY IS COMPLETE",
+        Outcome::Lost if results.light == 0 => "OUT OF LIGHT",
+        Outcome::Lost => "NO CONSTELLATIONS LEFT",
+    };
+    let lines = [
+        format!("CONSTELLATIONS: {}", results.completed_constellations),
+        format!("STARS USED: {}", results.stars_used),
+        format!("LIGHT LEFT: {}", results.light),
+    ];
+
+    let line_height = GLYPH_HEIGHT + 4;
+    draw_text_centered(title, top + 8, 3, fb);
+    for (idx, line) in lines.iter().enumerate() {
+        draw_text_centered(line, top + 24 + idx as i32 * line_height, 2, fb);
+    }
+    draw_text_centered("CLICK OR PRESS X FOR A NEW SKY", top + 58, 1, fb);
+}
+
+/// Height of a menu entry in pixels.
+pub(crate) const MENU_LINE_HEIGHT: i32 = GLYPH_HEIGHT + 5;
+
+/// Height of the title above the first menu entry, and of the space below the last one.
+const MENU_HEADER_HEIGHT: i32 = 28;
+const MENU_FOOTER_HEIGHT: i32 = 8;
+
+/// Top of the panel drawn by `render_menu`, which is centered on the screen.
+fn menu_panel_top(item_count: usize) -> i32 {
+    let height = MENU_HEADER_HEIGHT + item_count as i32 * MENU_LINE_HEIGHT + MENU_FOOTER_HEIGHT;
+    (SCREEN_SIZE as i32 - height) / 2
+}
+
+/// Top of the first entry of a menu with `item_count` entries, see `render_menu`.
+pub(crate) fn menu_top(item_count: usize) -> i32 {
+    menu_panel_top(item_count) + MENU_HEADER_HEIGHT
+}
+
+/// Draw a menu below a title. `label` provides the text of each entry,
+/// the selected entry is highlighted.
+pub(crate) fn render_menu<T: Copy>(
+    title: &str,
+    menu: &Menu<T>,
+    label: impl Fn(T) -> String,
+    fb: &mut impl Framebuffer,
+) {
+    let count = menu.items.len();
+    let top = menu_panel_top(count);
+    let height = MENU_HEADER_HEIGHT + count as i32 * MENU_LINE_HEIGHT + MENU_FOOTER_HEIGHT;
+    draw_panel(top, height as u32, fb);
+    draw_text_centered(title, top + 8, 3, fb);
+    for (idx, item) in menu.items.iter().enumerate() {
+        let y = menu_top(count) + idx as i32 * MENU_LINE_HEIGHT;
+        let text = label(*item);
+        if idx == menu.selected {
+            draw_text_centered(&format!("> {text} <"), y, 3, fb);
+        } else {
+            draw_text_centered(&text, y, 2, fb);
+        }
+    }
+}
+
+/// Horizontal position of the first digit on the seed selection screen.
+pub(crate) fn seed_digits_left() -> i32 {
+    (SCREEN_SIZE as i32 - text_width(&"0".repeat(SEED_DIGITS * 2 - 1))) / 2
+}
+
+/// Vertical position of the digits on the seed selection screen.
+pub(crate) const SEED_DIGITS_TOP: i32 = 76;
+
+/// Draw the seed selection screen, with markers above and below the selected digit.
+pub(crate) fn render_seed_selection(selection: &SeedSelection, fb: &mut impl Framebuffer) {
+    draw_panel(48, 64, fb);
+    draw_text_centered("CHOOSE A SKY", 56, 3, fb);
+
+    // Every second character is a space, to make the digits easier to tell apart.
+    let left = seed_digits_left();
+    let advance = 2 * (GLYPH_WIDTH + 1);
+    for (idx, c) in selection.text().chars().enumerate() {
+        let x = left + idx as i32 * advance;
+        let color = if idx == selection.digit { 3 } else { 2 };
+        draw_text(&c.to_string(), x, SEED_DIGITS_TOP, color, fb);
+        if idx == selection.digit {
+            fb.fill_rect(x, SEED_DIGITS_TOP - 3, GLYPH_WIDTH as u32, 1, 2);
+            fb.fill_rect(
+                x,
+                SEED_DIGITS_TOP + GLYPH_HEIGHT + 2,
+                GLYPH_WIDTH as u32,
+                1,
+                2,
+            );
+        }
+    }
+    draw_text_centered("CLICK OR PRESS X TO START", 96, 1, fb);
+}
+
+/// Draw the button that pauses the game, in the top right corner.
+pub(crate) fn render_pause_button(fb: &mut impl Framebuffer) {
+    let left = SCREEN_SIZE as i32 - PAUSE_BUTTON_SIZE;
+    fb.fill_rect(left + 2, 2, 2, 6, 2);
+    fb.fill_rect(left + 6, 2, 2, 6, 2);
+}
+
+/// Size of the pause button in pixels, see `render_pause_button`.
+pub(crate) const PAUSE_BUTTON_SIZE: i32 = 10;
+
+/// Scale of the overview in sky pixels per screen pixel, so that the whole sky fits on
+/// the screen. The minimap is scaled down by another `MINIMAP_SIZE_DIVISOR`.
+pub(crate) fn overview_scale(params: &SkyParams) -> i32 {
+    params.width().max(params.height()).div_ceil(SCREEN_SIZE) as i32
+}
+
+const MINIMAP_SIZE_DIVISOR: i32 = 4;
+
+/// Size of the minimap in pixels, and the position of its top left corner.
+pub(crate) const MINIMAP_SIZE: i32 = SCREEN_SIZE as i32 / MINIMAP_SIZE_DIVISOR;
+pub(crate) const MINIMAP_LEFT: i32 = SCREEN_SIZE as i32 - MINIMAP_SIZE - 2;
+pub(crate) const MINIMAP_TOP: i32 = SCREEN_SIZE as i32 - MINIMAP_SIZE - 2;
+
+/// The focus that centers the screen on the sky position shown at `(x, y)`
+/// in the overview, limited to the sky.
+pub(crate) fn focus_from_overview(params: &SkyParams, x: i32, y: i32) -> (i32, i32) {
+    let scale = overview_scale(params);
+    let half_screen = SCREEN_SIZE as i32 / 2;
+    let max_x = params.width() as i32 - SCREEN_SIZE as i32;
+    let max_y = params.height() as i32 - SCREEN_SIZE as i32;
+    (
+        (x * scale - half_screen).clamp(0, max_x.max(0)),
+        (y * scale - half_screen).clamp(0, max_y.max(0)),
+    )
+}
+
+/// Draw a single colour line between two points on the screen.
+fn draw_screen_line(x1: i32, y1: i32, x2: i32, y2: i32, color: u8, fb: &mut impl Framebuffer) {
+    let steps = (x2 - x1).abs().max((y2 - y1).abs()).max(1);
+    for i in 0..=steps {
+        let x = x1 + (x2 - x1) * i / steps;
+        let y = y1 + (y2 - y1) * i / steps;
+        fb.set_pixel(x, y, color);
+    }
+}
+
+/// Draw the sky at `1 / scale` of its size, with the top left corner at
+/// `(left, top)`, and the part of it that `view` shows as a rectangle.
+/// Stars that can still be linked are drawn in colour 3. With `details`,
+/// used up stars and all links are shown, otherwise only the links of
+/// completed constellations.
+fn draw_sky_scaled(
+    game: &Game,
+    view: &View,
+    scale: i32,
+    (left, top): (i32, i32),
+    details: bool,
+    fb: &mut impl Framebuffer,
+) {
+    let to_screen = |x: i16, y: i16| (left + x as i32 / scale, top + y as i32 / scale);
+    let threshold = game.params.constellation_threshold;
+    for link in &game.links {
+        let completed = game.constellations.size(link.start_idx as usize) >= threshold;
+        if !details && !completed {
+            continue;
+        }
+        let start = &game.stars[link.start_idx as usize];
+        let end = &game.stars[link.end_idx as usize];
+        let (x1, y1) = to_screen(start.x, start.y);
+        let (x2, y2) = to_screen(end.x, end.y);
+        let color = if link.style == STYLE_BRIGHT { 2 } else { 1 };
+        draw_screen_line(x1, y1, x2, y2, color, fb);
+    }
+    for star in &game.stars {
+        if star.bright || details {
+            let (x, y) = to_screen(star.x, star.y);
+            fb.set_pixel(x, y, if star.bright { 3 } else { 1 });
+        }
+    }
+
+    let (x, y) = (left + view.focus_x / scale, top + view.focus_y / scale);
+    let size = SCREEN_SIZE as i32 / scale;
+    fb.fill_rect(x, y, size as u32, 1, 2);
+    fb.fill_rect(x, y + size - 1, size as u32, 1, 2);
+    fb.fill_rect(x, y, 1, size as u32, 2);
+    fb.fill_rect(x + size - 1, y, 1, size as u32, 2);
+}
+
+/// Draw the minimap in the bottom right corner, with the bright stars,
+/// the completed constellations and the part of the sky on screen.
+pub(crate) fn render_minimap(game: &Game, view: &View, fb: &mut impl Framebuffer) {
+    let scale = overview_scale(&game.params) * MINIMAP_SIZE_DIVISOR;
+    let size = MINIMAP_SIZE as u32;
+    fb.fill_rect(MINIMAP_LEFT - 1, MINIMAP_TOP - 1, size + 2, size + 2, 1);
+    fb.fill_rect(MINIMAP_LEFT, MINIMAP_TOP, size, size, 0);
+    draw_sky_scaled(game, view, scale, (MINIMAP_LEFT, MINIMAP_TOP), false, fb);
+}
+
+/// Draw the whole sky scaled down to the screen.
+pub(crate) fn render_overview(game: &Game, view: &View, fb: &mut impl Framebuffer) {
+    fb.clear();
+    draw_sky_scaled(game, view, overview_scale(&game.params), (0, 0), true, fb);
+}
+
+/// Darken the screen while fading into a new scene, `frames_left`
+/// frames before the fade is complete.
+pub(crate) fn render_transition(frames_left: u32, fb: &mut impl Framebuffer) {
+    const BAYER: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+    let coverage = frames_left.min(TRANSITION_FRAMES) * 16 / TRANSITION_FRAMES;
+    if coverage == 0 {
+        return;
+    }
+    for y in 0..SCREEN_SIZE as i32 {
+        for x in 0..SCREEN_SIZE as i32 {
+            if BAYER[y as usize % 4][x as usize % 4] < coverage {
+                fb.set_pixel(x, y, 0);
+            }
+        }
+    }
+}
+
+pub(crate) fn render_draft_line(game: &Game, view: &View, fb: &mut impl Framebuffer) {
+    if let Some(draft) = &game.draft {
+        let start = &game.stars[draft.start_idx as usize];
+        let style = if game.draft_rejection().is_some() {
+            STYLE_REJECTED
+        } else {
+            STYLE_DASHED
+        };
+        draw_line(start.x, start.y, draft.end_x, draft.end_y, style, view, fb);
+    }
+}
+
+/// Number of frames it takes a cancelled draft line to retract.
+pub(crate) const CANCEL_ANIMATION_FRAMES: u32 = 12;
+
+/// Draw a cancelled draft line, `frame` frames after it was cancelled.
+/// The line retracts towards its start star.
+pub(crate) fn render_cancelled_draft(
+    game: &Game,
+    start_idx: u16,
+    end_x: i16,
+    end_y: i16,
+    frame: u32,
+    view: &View,
+    fb: &mut impl Framebuffer,
+) {
+    let start = &game.stars[start_idx as usize];
+    let remaining = CANCEL_ANIMATION_FRAMES.saturating_sub(frame) as i32;
+    let scale = |from: i16, to: i16| {
+        from + ((to - from) as i32 * remaining / CANCEL_ANIMATION_FRAMES as i32) as i16
+    };
+    draw_line(
+        start.x,
+        start.y,
+        scale(start.x, end_x),
+        scale(start.y, end_y),
+        STYLE_DASHED,
+        view,
+        fb,
+    );
+}
+
+/// Number of frames during which the links of a completed constellation
+/// shine and its stars flash.
+pub(crate) const COMPLETION_WAVE_FRAMES: u32 = 24;
+
+/// Total number of frames of the completion animation. After the wave,
+/// the refunded light flies into the HUD.
+pub(crate) const COMPLETION_ANIMATION_FRAMES: u32 = 48;
+
+/// A constellation that was just completed, while it is being animated.
+pub(crate) struct Completion {
+    stars: Vec<u16>,
+    links: Vec<u16>,
+    light: u8,
+    pub frame: u32,
+}
+
+impl Completion {
+    /// Start the animation for the constellation of `star_idx`, which refunded `light`.
+    pub(crate) fn new(game: &Game, star_idx: u16, light: u8) -> Completion {
+        Completion {
+            stars: game.constellations.stars(star_idx as usize).to_vec(),
+            links: game.constellations.links(star_idx as usize).to_vec(),
+            light,
+            frame: 0,
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.frame >= COMPLETION_ANIMATION_FRAMES
+    }
+
+    /// The refunded light which has not arrived at the HUD yet.
+    pub(crate) fn pending_light(&self) -> u8 {
+        if self.is_done() { 0 } else { self.light }
+    }
+}
+
+/// Draw the completion animation on top of the sky. `light` is the amount of
+/// light shown in the HUD, which the refunded light flies towards.
+pub(crate) fn render_completion(
+    game: &Game,
+    completion: &Completion,
+    light: u8,
+    view: &View,
+    fb: &mut impl Framebuffer,
+) {
+    if completion.frame < COMPLETION_WAVE_FRAMES {
+        // The shine of bright links travels 2 pixels per frame,
+        // so it runs along every link once during the wave.
+        let wave_view = View {
+            frame: completion.frame,
+            ..*view
+        };
+        for link_idx in &completion.links {
+            let link = &game.links[*link_idx as usize];
+            let start = &game.stars[link.start_idx as usize];
+            let end = &game.stars[link.end_idx as usize];
+            draw_line(start.x, start.y, end.x, end.y, STYLE_BRIGHT, &wave_view, fb);
+        }
+
+        if (completion.frame / 4).is_multiple_of(2) {
+            for star_idx in &completion.stars {
+                let mut star = game.stars[*star_idx as usize].clone();
+                star.bright = true;
+                star.render(view, fb);
+            }
+        }
+        return;
+    }
+
+    // Fly from the center of the constellation to the next free slots of the HUD.
+    let count = completion.stars.len() as i32;
+    let (sum_x, sum_y) = completion.stars.iter().fold((0, 0), |(x, y), idx| {
+        let star = &game.stars[*idx as usize];
+        (x + star.x as i32, y + star.y as i32)
+    });
+    let from_x = sum_x / count - view.focus_x;
+    let from_y = sum_y / count - view.focus_y;
+
+    let progress = (completion.frame - COMPLETION_WAVE_FRAMES) as i32;
+    let duration = (COMPLETION_ANIMATION_FRAMES - COMPLETION_WAVE_FRAMES) as i32;
+    for i in 0..completion.light as i32 {
+        let to_x = 4 + (light as i32 + i) * 8;
+        let to_y = SCREEN_SIZE as i32 - 6;
+        let x = from_x + (to_x - from_x) * progress / duration;
+        let y = from_y + (to_y - from_y) * progress / duration;
+        fb.fill_rect(x, y, 2, 2, 3);
+    }
+}
+
+pub(crate) fn render_sky(game: &Game, settings: &Settings, view: &View, fb: &mut impl Framebuffer) {
+    // Stars are at most 9x9 pixels, so only stars within a few
+    // pixels of the screen can be visible.
+    let (left, top) = (view.focus_x - 4, view.focus_y - 4);
+    let (right, bottom) = (left + SCREEN_SIZE as i32 + 8, top + SCREEN_SIZE as i32 + 8);
+    for idx in game.index.stars_in_rect(left, top, right, bottom) {
+        if settings.accessible {
+            game.stars[idx].render_shape(view, fb);
+        } else {
+            game.stars[idx].render(view, fb);
+        }
+    }
+    if settings.show_hints {
+        for idx in game.draft_targets() {
+            game.stars[idx].render_target_pulse(view, fb);
+        }
+    }
+
+    for link in &game.links {
+        let start = &game.stars[link.start_idx as usize];
+        let end = &game.stars[link.end_idx as usize];
+        let off_screen = (start.x as i32).max(end.x as i32) < left
+            || (start.x as i32).min(end.x as i32) > right
+            || (start.y as i32).max(end.y as i32) < top
+            || (start.y as i32).min(end.y as i32) > bottom;
+        if !off_screen {
+            draw_line(start.x, start.y, end.x, end.y, link.style, view, fb);
+        }
+    }
+}
+
+/// Render a complete frame of the game, in the same order
+/// as the nodes in the project.
+pub(crate) fn render_game(
+    game: &Game,
+    settings: &Settings,
+    view: &View,
+    fb: &mut impl Framebuffer,
+) {
+    fb.clear();
+    render_draft_line(game, view, fb);
+    if let Some(idx) = game.cursor_star() {
+        render_cursor(game.stars[idx].x, game.stars[idx].y, view, fb);
+    }
+    render_sky(game, settings, view, fb);
+    render_hud(game.light, fb);
+    if let Some(results) = game.results() {
+        render_results(&results, fb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::framebuffer::MemoryFramebuffer;
+    use crate::game::tests::{drag, find_completing_pair};
+    use crate::game::{GameEvent, InputEvent};
+    use crate::scene::{PauseItem, TitleItem, pause_label, title_label};
+
+    /// Compare a rendered frame against the reference image `tests/golden/<name>.pgm`.
+    /// Run the tests with `UPDATE_GOLDEN=1` to (re)create the reference images.
+    fn check_golden(name: &str, game: &Game, view: View) {
+        let mut fb = MemoryFramebuffer::new();
+        render_game(game, &Settings::DEFAULT, &view, &mut fb);
+        check_golden_fb(name, &fb);
+    }
+
+    /// Compare an already rendered frame against a reference image, see `check_golden`.
+    fn check_golden_fb(name: &str, fb: &MemoryFramebuffer) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{name}.pgm"));
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, fb.to_pgm()).unwrap();
+            return;
+        }
+
+        let data = std::fs::read(&path)
+            .unwrap_or_else(|_| panic!("Missing reference image {}", path.display()));
+        let expected = MemoryFramebuffer::from_pgm(&data)
+            .unwrap_or_else(|| panic!("Invalid reference image {}", path.display()));
+
+        let mut mismatches = 0;
+        for y in 0..SCREEN_SIZE {
+            for x in 0..SCREEN_SIZE {
+                if fb.get_pixel(x, y) != expected.get_pixel(x, y) {
+                    mismatches += 1;
+                }
+            }
+        }
+
+        if mismatches > 0 {
+            let actual_path = path.with_extension("actual.pgm");
+            std::fs::write(&actual_path, fb.to_pgm()).unwrap();
+            panic!(
+                "{mismatches} pixels differ from {}, output written to {}",
+                path.display(),
+                actual_path.display()
+            );
+        }
+    }
+
+    /// Find a bright star that is shown on screen for the given view.
+    fn star_in_view(game: &Game, view: &View) -> usize {
+        let margin = 40;
+        game.stars
+            .iter()
+            .position(|star| {
+                star.bright
+                    && star.x as i32 - view.focus_x >= margin
+                    && star.x as i32 - view.focus_x < SCREEN_SIZE as i32 - margin
+                    && star.y as i32 - view.focus_y >= margin
+                    && star.y as i32 - view.focus_y < SCREEN_SIZE as i32 - margin
+            })
+            .expect("No star in view")
+    }
+
+    #[test]
+    fn golden_initial_sky() {
+        let game = Game::new(123456, SkyParams::STANDARD);
+        let view = View {
+            focus_x: 240,
+            focus_y: 240,
+            frame: 0,
+            reduce_motion: false,
+        };
+        check_golden("initial_sky", &game, view);
+    }
+
+    #[test]
+    fn golden_shine_animation() {
+        let game = Game::new(123456, SkyParams::STANDARD);
+        let view = View {
+            focus_x: 240,
+            focus_y: 240,
+            frame: 9,
+            reduce_motion: false,
+        };
+        check_golden("shine_animation", &game, view);
+    }
+
+    #[test]
+    fn golden_draft_line() {
+        let mut game = Game::new(987654, SkyParams::STANDARD);
+        let view = View {
+            focus_x: 200,
+            focus_y: 260,
+            frame: 37,
+            reduce_motion: false,
+        };
+        let idx = star_in_view(&game, &view);
+        let (x, y) = (game.stars[idx].x, game.stars[idx].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        game.handle_input(InputEvent::MoveTo {
+            x: x + 25,
+            y: y + 9,
+        });
+        check_golden("draft_line", &game, view);
+    }
+
+    #[test]
+    fn golden_accessible_draft_line() {
+        let mut game = Game::new(987654, SkyParams::STANDARD);
+        let view = View {
+            focus_x: 200,
+            focus_y: 260,
+            frame: 37,
+            reduce_motion: true,
+        };
+        let idx = star_in_view(&game, &view);
+        let (x, y) = (game.stars[idx].x, game.stars[idx].y);
+        game.handle_input(InputEvent::MoveTo { x, y });
+        game.handle_input(InputEvent::Press);
+        game.handle_input(InputEvent::MoveTo {
+            x: x + 25,
+            y: y + 9,
+        });
+        let settings = Settings {
+            accessible: true,
+            ..Settings::DEFAULT
+        };
+        let mut fb = MemoryFramebuffer::new();
+        render_game(&game, &settings, &view, &mut fb);
+        check_golden_fb("accessible_draft_line", &fb);
+
+        // Without motion, the frame counter makes no difference.
+        let mut later = MemoryFramebuffer::new();
+        render_game(&game, &settings, &View { frame: 90, ..view }, &mut later);
+        assert_eq!(later.to_pgm(), fb.to_pgm());
+    }
+
+    /// Add a link that completes a constellation, and start its animation.
+    fn complete_constellation(game: &mut Game) -> Completion {
+        let (a, b) = find_completing_pair(game);
+        for event in drag(game, a, b) {
+            if let GameEvent::ConstellationCompleted {
+                star_idx, light, ..
+            } = event
+            {
+                return Completion::new(game, star_idx, light);
+            }
+        }
+        panic!("Link did not complete a constellation");
+    }
+
+    #[test]
+    fn completion_holds_back_light() {
+        let params = SkyParams {
+            constellation_threshold: 4,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params);
+        let mut completion = complete_constellation(&mut game);
+        assert!(completion.stars.len() >= 4);
+        assert_eq!(completion.pending_light(), completion.light);
+
+        completion.frame = COMPLETION_ANIMATION_FRAMES;
+        assert!(completion.is_done());
+        assert_eq!(completion.pending_light(), 0);
+    }
+
+    #[test]
+    fn golden_completion() {
+        let params = SkyParams {
+            constellation_threshold: 4,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params);
+        let mut completion = complete_constellation(&mut game);
+        completion.frame = 8;
+
+        let star = &game.stars[completion.stars[0] as usize];
+        let view = View {
+            focus_x: star.x as i32 - SCREEN_SIZE as i32 / 2,
+            focus_y: star.y as i32 - SCREEN_SIZE as i32 / 2,
+            frame: 100,
+            reduce_motion: false,
+        };
+        let mut fb = MemoryFramebuffer::new();
+        render_game(&game, &Settings::DEFAULT, &view, &mut fb);
+        render_completion(&game, &completion, game.light, &view, &mut fb);
+        check_golden_fb("completion", &fb);
+    }
+
+    #[test]
+    fn golden_minimap_and_overview() {
+        let params = SkyParams {
+            constellation_threshold: 4,
+            ..SkyParams::STANDARD
+        };
+        let mut game = Game::new(123456, params);
+        let completion = complete_constellation(&mut game);
+        let star = &game.stars[completion.stars[0] as usize];
+        let view = View {
+            focus_x: star.x as i32 - SCREEN_SIZE as i32 / 2,
+            focus_y: star.y as i32 - SCREEN_SIZE as i32 / 2,
+            frame: 0,
+            reduce_motion: false,
+        };
+
+        let mut fb = MemoryFramebuffer::new();
+        render_game(&game, &Settings::DEFAULT, &view, &mut fb);
+        render_minimap(&game, &view, &mut fb);
+        check_golden_fb("minimap", &fb);
+
+        render_overview(&game, &view, &mut fb);
+        check_golden_fb("overview", &fb);
+    }
+
+    #[test]
+    fn overview_positions_map_to_focus() {
+        let params = SkyParams::STANDARD;
+        assert_eq!(overview_scale(&params), 4);
+        assert_eq!(focus_from_overview(&params, 80, 80), (240, 240));
+        assert_eq!(focus_from_overview(&params, 0, 159), (0, 480));
+
+        let scale = overview_scale(&SkyParams::HUGE);
+        assert!(SkyParams::HUGE.width() as i32 <= SCREEN_SIZE as i32 * scale);
+    }
+
+    #[test]
+    fn golden_title() {
+        let game = Game::new(123456, SkyParams::STANDARD);
+        let mut fb = MemoryFramebuffer::new();
+        render_game(
+            &game,
+            &Settings::DEFAULT,
+            &View {
+                focus_x: 240,
+                focus_y: 240,
+                frame: 0,
+                reduce_motion: false,
+            },
+            &mut fb,
+        );
+        let mut menu = Menu::new(vec![TitleItem::Continue, TitleItem::NewSky]);
+        menu.selected = 1;
+        render_menu("THE ART OF CONSTELLATION", &menu, title_label, &mut fb);
+        check_golden_fb("title", &fb);
+    }
+
+    #[test]
+    fn golden_pause_menu() {
+        let mut fb = MemoryFramebuffer::new();
+        let mut menu = Menu::new(PauseItem::all());
+        menu.selected = 3;
+        let settings = Settings {
+            edge_scroll: false,
+            ..Settings::DEFAULT
+        };
+        render_menu(
+            "PAUSED",
+            &menu,
+            |item| pause_label(item, &settings),
+            &mut fb,
+        );
+        check_golden_fb("pause_menu", &fb);
+    }
+
+    #[test]
+    fn golden_seed_selection() {
+        let mut fb = MemoryFramebuffer::new();
+        let mut selection = SeedSelection::new(0x0001_E240);
+        selection.digit = 3;
+        render_seed_selection(&selection, &mut fb);
+        check_golden_fb("seed_selection", &fb);
+    }
+
+    #[test]
+    fn transition_fades_in() {
+        let game = Game::new(123456, SkyParams::STANDARD);
+        let view = View {
+            focus_x: 240,
+            focus_y: 240,
+            frame: 0,
+            reduce_motion: false,
+        };
+        let mut fb = MemoryFramebuffer::new();
+        render_game(&game, &Settings::DEFAULT, &view, &mut fb);
+        let reference = fb.to_pgm();
+
+        render_transition(0, &mut fb);
+        assert_eq!(fb.to_pgm(), reference);
+
+        render_transition(TRANSITION_FRAMES, &mut fb);
+        for y in 0..SCREEN_SIZE {
+            for x in 0..SCREEN_SIZE {
+                assert_eq!(fb.get_pixel(x, y), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn golden_results() {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        game.light = 0;
+        game.completed_constellations = 2;
+        game.stars_used = 17;
+        let view = View {
+            focus_x: 240,
+            focus_y: 240,
+            frame: 0,
+            reduce_motion: false,
+        };
+        check_golden("results", &game, view);
+    }
+}
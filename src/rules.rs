@@ -0,0 +1,143 @@
+use crate::line::Link;
+use crate::star::Star;
+
+/// Which links the player is allowed to add. Each game mode
+/// (see the presets of `SkyParams`) has its own set of rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LinkRules {
+    /// Reject links between two stars that are already linked directly.
+    pub forbid_duplicates: bool,
+    /// Reject links that cross an existing link.
+    pub forbid_crossing: bool,
+}
+
+impl LinkRules {
+    pub(crate) const STRICT: LinkRules = LinkRules {
+        forbid_duplicates: true,
+        forbid_crossing: true,
+    };
+
+    pub(crate) const RELAXED: LinkRules = LinkRules {
+        forbid_duplicates: true,
+        forbid_crossing: false,
+    };
+}
+
+/// Why a link was not added.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum LinkRejection {
+    /// The two stars are already linked.
+    Duplicate { link_idx: u16 },
+    /// The link would cross an existing link.
+    Crossing { link_idx: u16 },
+}
+
+/// Sign of the cross product of `b - a` and `c - a`.
+fn orientation(a: (i32, i32), b: (i32, i32), c: (i32, i32)) -> i32 {
+    ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).signum()
+}
+
+/// Whether `p`, which is collinear with `a` and `b`, lies on the segment between them.
+fn is_on_segment(a: (i32, i32), b: (i32, i32), p: (i32, i32)) -> bool {
+    p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0) && p.1 >= a.1.min(b.1) && p.1 <= a.1.max(b.1)
+}
+
+/// Whether the segments `a1-a2` and `b1-b2` intersect. Segments that only
+/// touch at a shared end point do not count as crossing.
+pub(crate) fn segments_cross(
+    a1: (i32, i32),
+    a2: (i32, i32),
+    b1: (i32, i32),
+    b2: (i32, i32),
+) -> bool {
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+    if d1 * d2 < 0 && d3 * d4 < 0 {
+        return true;
+    }
+
+    // Collinear cases, where an end point lies on the other segment.
+    let touches = |d: i32, s1, s2, p| d == 0 && p != s1 && p != s2 && is_on_segment(s1, s2, p);
+    touches(d1, b1, b2, a1)
+        || touches(d2, b1, b2, a2)
+        || touches(d3, a1, a2, b1)
+        || touches(d4, a1, a2, b2)
+}
+
+/// Check a new link between `start_idx` and `end_idx` against the existing `links`.
+pub(crate) fn check_link(
+    rules: &LinkRules,
+    stars: &[Star],
+    links: &[Link],
+    start_idx: u16,
+    end_idx: u16,
+) -> Result<(), LinkRejection> {
+    let pos = |idx: u16| (stars[idx as usize].x as i32, stars[idx as usize].y as i32);
+    let (start, end) = (pos(start_idx), pos(end_idx));
+
+    for (link_idx, link) in links.iter().enumerate() {
+        let link_idx = link_idx as u16;
+        let is_duplicate = (link.start_idx == start_idx && link.end_idx == end_idx)
+            || (link.start_idx == end_idx && link.end_idx == start_idx);
+        if is_duplicate {
+            if rules.forbid_duplicates {
+                return Err(LinkRejection::Duplicate { link_idx });
+            }
+            continue;
+        }
+
+        if rules.forbid_crossing
+            && segments_cross(start, end, pos(link.start_idx), pos(link.end_idx))
+        {
+            return Err(LinkRejection::Crossing { link_idx });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::STYLE_BRIGHT;
+
+    #[test]
+    fn crossing_segments() {
+        assert!(segments_cross((0, 0), (10, 10), (0, 10), (10, 0)));
+        assert!(!segments_cross((0, 0), (10, 10), (20, 0), (30, 10)));
+        assert!(!segments_cross((0, 0), (10, 0), (0, 5), (10, 5)));
+        // Shared end points are fine, overlapping segments are not.
+        assert!(!segments_cross((0, 0), (10, 10), (10, 10), (20, 0)));
+        assert!(segments_cross((0, 0), (10, 0), (5, 0), (20, 0)));
+        assert!(segments_cross((0, 0), (20, 0), (0, 0), (10, 0)));
+        // T-junction.
+        assert!(segments_cross((0, 0), (10, 0), (5, 0), (5, 10)));
+    }
+
+    #[test]
+    fn checks_links() {
+        let stars = vec![
+            Star::new(0, 0, true),
+            Star::new(20, 20, true),
+            Star::new(0, 20, true),
+            Star::new(20, 0, true),
+            Star::new(40, 0, true),
+        ];
+        let links = vec![Link::new(0, 1, STYLE_BRIGHT)];
+        let strict = LinkRules::STRICT;
+        let relaxed = LinkRules::RELAXED;
+
+        assert_eq!(
+            check_link(&strict, &stars, &links, 1, 0),
+            Err(LinkRejection::Duplicate { link_idx: 0 })
+        );
+        assert_eq!(
+            check_link(&strict, &stars, &links, 2, 3),
+            Err(LinkRejection::Crossing { link_idx: 0 })
+        );
+        assert_eq!(check_link(&relaxed, &stars, &links, 2, 3), Ok(()));
+        assert_eq!(check_link(&strict, &stars, &links, 1, 3), Ok(()));
+        assert_eq!(check_link(&strict, &stars, &links, 3, 4), Ok(()));
+    }
+}
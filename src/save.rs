@@ -0,0 +1,363 @@
+use crate::constellations::Constellations;
+use crate::game::Game;
+use crate::line::{Link, STYLE_BRIGHT, STYLE_DIM};
+use crate::rules::LinkRules;
+use crate::settings::{Settings, theme_from_u8, theme_to_u8};
+use crate::util::SkyParams;
+
+/// Size of the WASM-4 persistent disk.
+pub(crate) const DISK_SIZE: usize = 1024;
+
+const MAGIC: &[u8; 3] = b"AoC";
+
+/// Bump this whenever the layout of the save data or the output
+/// of `generate_sky` changes, so that old saves are ignored.
+const VERSION: u8 = 7;
+
+/// Magic, version and payload length.
+const HEADER_SIZE: usize = 6;
+const CHECKSUM_SIZE: usize = 4;
+
+// Layout of the payload, all values are little endian:
+//
+// settings: see `write_settings`
+// seed: u32
+// params: see `write_params`
+// light: u8
+// completed constellations: u16
+// stars used: u16
+// star count: u16
+// star brightness: one bit per star
+// player link count: u16
+// player links: (start_idx: u16, end_idx: u16) per link
+//
+// Preset links and the sky itself are regenerated from the seed,
+// link styles are derived from the brightness of the stars.
+
+fn checksum(data: &[u8]) -> u32 {
+    // FNV-1a
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in data {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (out, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(out)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+}
+
+fn write_params(out: &mut Vec<u8>, params: &SkyParams) {
+    out.push(params.width_sections as u8);
+    out.push(params.height_sections as u8);
+    out.push(params.section_width as u8);
+    out.push(params.section_height as u8);
+    out.extend_from_slice(&(params.max_stars as u16).to_le_bytes());
+    out.push(params.star_dist_min as u8);
+    out.push(params.star_dist_max_for_preset_line as u8);
+    out.push(params.star_dist_max_for_line as u8);
+    out.push(params.star_dist_dead_zone_end as u8);
+    out.extend_from_slice(&params.preset_line_chance.to_bits().to_le_bytes());
+    out.push(params.max_preset_constellation_size as u8);
+    out.push(params.constellation_threshold as u8);
+    out.push(
+        params.link_rules.forbid_duplicates as u8 | (params.link_rules.forbid_crossing as u8) << 1,
+    );
+}
+
+fn read_params(reader: &mut Reader) -> Option<SkyParams> {
+    Some(SkyParams {
+        width_sections: reader.u8()? as usize,
+        height_sections: reader.u8()? as usize,
+        section_width: reader.u8()? as usize,
+        section_height: reader.u8()? as usize,
+        max_stars: reader.u16()? as usize,
+        star_dist_min: reader.u8()? as usize,
+        star_dist_max_for_preset_line: reader.u8()? as usize,
+        star_dist_max_for_line: reader.u8()? as usize,
+        star_dist_dead_zone_end: reader.u8()? as usize,
+        preset_line_chance: f32::from_bits(reader.u32()?),
+        max_preset_constellation_size: reader.u8()? as usize,
+        constellation_threshold: reader.u8()? as usize,
+        link_rules: {
+            let flags = reader.u8()?;
+            LinkRules {
+                forbid_duplicates: flags & 1 != 0,
+                forbid_crossing: flags & 2 != 0,
+            }
+        },
+    })
+}
+
+fn write_settings(out: &mut Vec<u8>, settings: &Settings) {
+    let mut flags = 0;
+    if settings.block_input_during_effects {
+        flags |= 1;
+    }
+    if settings.edge_scroll {
+        flags |= 2;
+    }
+    if settings.show_hints {
+        flags |= 4;
+    }
+    if settings.accessible {
+        flags |= 8;
+    }
+    out.push(flags);
+    out.push(settings.scroll_speed);
+    out.push(settings.volume);
+    out.push(theme_to_u8(settings.theme));
+}
+
+fn read_settings(reader: &mut Reader) -> Option<Settings> {
+    let flags = reader.u8()?;
+    let settings = Settings {
+        block_input_during_effects: flags & 1 != 0,
+        edge_scroll: flags & 2 != 0,
+        show_hints: flags & 4 != 0,
+        accessible: flags & 8 != 0,
+        scroll_speed: reader.u8()?,
+        volume: reader.u8()?,
+        theme: theme_from_u8(reader.u8()?),
+    };
+    settings.is_valid().then_some(settings)
+}
+
+/// Serialize the state of a game, together with the settings.
+/// Returns `None` if the result would not fit on the disk.
+pub(crate) fn encode_game(game: &Game, settings: &Settings) -> Option<Vec<u8>> {
+    let mut payload = Vec::new();
+    write_settings(&mut payload, settings);
+    payload.extend_from_slice(&game.seed.to_le_bytes());
+    write_params(&mut payload, &game.params);
+    payload.push(game.light);
+    payload.extend_from_slice(&game.completed_constellations.to_le_bytes());
+    payload.extend_from_slice(&game.stars_used.to_le_bytes());
+
+    payload.extend_from_slice(&(game.stars.len() as u16).to_le_bytes());
+    for chunk in game.stars.chunks(8) {
+        let mut byte = 0;
+        for (bit, star) in chunk.iter().enumerate() {
+            if star.bright {
+                byte |= 1 << bit;
+            }
+        }
+        payload.push(byte);
+    }
+
+    let player_links = &game.links[game.preset_link_count..];
+    payload.extend_from_slice(&(player_links.len() as u16).to_le_bytes());
+    for link in player_links {
+        payload.extend_from_slice(&link.start_idx.to_le_bytes());
+        payload.extend_from_slice(&link.end_idx.to_le_bytes());
+    }
+
+    if HEADER_SIZE + payload.len() + CHECKSUM_SIZE > DISK_SIZE {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + payload.len() + CHECKSUM_SIZE);
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    out.extend_from_slice(&payload);
+    out.extend_from_slice(&checksum(&out).to_le_bytes());
+    Some(out)
+}
+
+/// Restore a game and the settings written by `encode_game`. Returns `None`
+/// if the data is empty, corrupt or was written by a different version.
+pub(crate) fn decode_game(data: &[u8]) -> Option<(Game, Settings)> {
+    let mut reader = Reader { data };
+    if reader.bytes(MAGIC.len())? != MAGIC || reader.u8()? != VERSION {
+        return None;
+    }
+    let payload_len = reader.u16()? as usize;
+    let payload = reader.bytes(payload_len)?;
+    let stored_checksum = reader.u32()?;
+    if checksum(&data[..HEADER_SIZE + payload_len]) != stored_checksum {
+        return None;
+    }
+
+    let mut reader = Reader { data: payload };
+    let settings = read_settings(&mut reader)?;
+    let seed = reader.u32()?;
+    let params = read_params(&mut reader)?;
+    if !params.is_valid() {
+        return None;
+    }
+    let light = reader.u8()?;
+    let completed_constellations = reader.u16()?;
+    let stars_used = reader.u16()?;
+
+    let star_count = reader.u16()? as usize;
+    if star_count > params.max_stars {
+        return None;
+    }
+    let brightness = reader.bytes(star_count.div_ceil(8))?.to_vec();
+
+    let link_count = reader.u16()? as usize;
+    let mut player_links = Vec::with_capacity(link_count);
+    for _ in 0..link_count {
+        let start_idx = reader.u16()?;
+        let end_idx = reader.u16()?;
+        if start_idx as usize >= star_count || end_idx as usize >= star_count {
+            return None;
+        }
+        player_links.push(Link::new(start_idx, end_idx, STYLE_BRIGHT));
+    }
+
+    let mut game = Game::new(seed, params);
+    if game.stars.len() != star_count {
+        return None;
+    }
+    game.light = light;
+    game.completed_constellations = completed_constellations;
+    game.stars_used = stars_used;
+    for (idx, star) in game.stars.iter_mut().enumerate() {
+        star.bright = brightness[idx / 8] & (1 << (idx % 8)) != 0;
+    }
+    game.links.extend(player_links);
+    game.constellations = Constellations::new(game.stars.len(), &game.links);
+    for link in game.links.iter_mut() {
+        let bright =
+            game.stars[link.start_idx as usize].bright && game.stars[link.end_idx as usize].bright;
+        link.style = if bright { STYLE_BRIGHT } else { STYLE_DIM };
+    }
+    Some((game, settings))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn save_to_disk(game: &Game, settings: &Settings) {
+    // If the game does not fit, clear the disk instead of
+    // leaving an outdated save behind.
+    let data = encode_game(game, settings).unwrap_or_default();
+    wasm4_target::diskw(&data);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load_from_disk() -> Option<(Game, Settings)> {
+    let mut data = [0; DISK_SIZE];
+    let len = wasm4_target::diskr(&mut data) as usize;
+    decode_game(&data[..len.min(DISK_SIZE)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::InputEvent;
+    use crate::palette::Theme;
+
+    /// Play a few moves, so that the save contains player links
+    /// and a changed light count.
+    fn played_game() -> Game {
+        let mut game = Game::new(123456, SkyParams::STANDARD);
+        let max_dist_sq = (game.params.star_dist_max_for_line.pow(2)) as i32;
+        let mut moves = 0;
+        for a in 0..game.stars.len() {
+            for b in 0..game.stars.len() {
+                let (star_a, star_b) = (&game.stars[a], &game.stars[b]);
+                let dx = (star_a.x - star_b.x) as i32;
+                let dy = (star_a.y - star_b.y) as i32;
+                if a == b || !star_a.bright || !star_b.bright || dx * dx + dy * dy > max_dist_sq {
+                    continue;
+                }
+
+                let (x1, y1, x2, y2) = (star_a.x, star_a.y, star_b.x, star_b.y);
+                game.handle_input(InputEvent::MoveTo { x: x1, y: y1 });
+                game.handle_input(InputEvent::Press);
+                game.handle_input(InputEvent::MoveTo { x: x2, y: y2 });
+                game.handle_input(InputEvent::Release);
+                moves += 1;
+                if moves == 5 {
+                    return game;
+                }
+            }
+        }
+        game
+    }
+
+    #[test]
+    fn roundtrip() {
+        let game = played_game();
+        let settings = Settings {
+            scroll_speed: 3,
+            edge_scroll: false,
+            volume: 1,
+            theme: Some(Theme::EInk),
+            accessible: true,
+            ..Settings::DEFAULT
+        };
+        let data = encode_game(&game, &settings).unwrap();
+        assert!(data.len() <= DISK_SIZE);
+
+        let (restored, restored_settings) = decode_game(&data).unwrap();
+        assert_eq!(restored_settings, settings);
+        assert_eq!(restored.seed, game.seed);
+        assert_eq!(restored.params, game.params);
+        assert_eq!(restored.light, game.light);
+        assert_eq!(
+            restored.completed_constellations,
+            game.completed_constellations
+        );
+        assert_eq!(restored.stars_used, game.stars_used);
+        assert_eq!(restored.stars, game.stars);
+        assert_eq!(restored.links, game.links);
+        assert_eq!(restored.preset_link_count, game.preset_link_count);
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let data = encode_game(&played_game(), &Settings::DEFAULT).unwrap();
+
+        assert!(decode_game(&[]).is_none());
+        assert!(decode_game(&[0; DISK_SIZE]).is_none());
+        assert!(decode_game(&data[..data.len() - 1]).is_none());
+
+        for idx in [0, 3, 4, 10, data.len() / 2, data.len() - 1] {
+            let mut corrupt = data.clone();
+            corrupt[idx] ^= 0x10;
+            assert!(decode_game(&corrupt).is_none(), "Flipped bit in byte {idx}");
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut data = encode_game(&played_game(), &Settings::DEFAULT).unwrap();
+        data[3] = VERSION + 1;
+        let len = data.len();
+        let checksum = checksum(&data[..len - CHECKSUM_SIZE]);
+        data[len - CHECKSUM_SIZE..].copy_from_slice(&checksum.to_le_bytes());
+        assert!(decode_game(&data).is_none());
+    }
+
+    #[test]
+    fn huge_sky_fits_on_disk() {
+        let game = Game::new(1, SkyParams::HUGE);
+        assert!(encode_game(&game, &Settings::DEFAULT).is_some());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::settings::{Setting, Settings};
+use crate::util::next_random;
+
+/// The screens of the game. Each scene decides which nodes receive input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Scene {
+    Title,
+    SeedSelection,
+    Playing,
+    Paused,
+    /// The whole sky at a smaller scale, for moving the camera far.
+    Overview,
+    Results,
+}
+
+/// Number of frames it takes to fade into a new scene.
+pub(crate) const TRANSITION_FRAMES: u32 = 16;
+
+/// Entries of the title menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TitleItem {
+    Continue,
+    NewSky,
+}
+
+/// The text of an entry of the title menu.
+pub(crate) fn title_label(item: TitleItem) -> String {
+    match item {
+        TitleItem::Continue => "CONTINUE".to_owned(),
+        TitleItem::NewSky => "NEW SKY".to_owned(),
+    }
+}
+
+/// Entries of the pause menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PauseItem {
+    Resume,
+    Setting(Setting),
+    QuitToTitle,
+}
+
+impl PauseItem {
+    /// All entries, in the order they are shown.
+    pub(crate) fn all() -> Vec<PauseItem> {
+        let mut items = vec![PauseItem::Resume];
+        items.extend(Setting::ALL.map(PauseItem::Setting));
+        items.push(PauseItem::QuitToTitle);
+        items
+    }
+}
+
+/// The text of an entry of the pause menu, including the current value of a setting.
+pub(crate) fn pause_label(item: PauseItem, settings: &Settings) -> String {
+    match item {
+        PauseItem::Resume => "RESUME".to_owned(),
+        PauseItem::Setting(setting) => setting.label(settings),
+        PauseItem::QuitToTitle => "QUIT TO TITLE".to_owned(),
+    }
+}
+
+/// A vertical list of entries, one of which is selected.
+pub(crate) struct Menu<T> {
+    pub items: Vec<T>,
+    pub selected: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    pub(crate) fn new(items: Vec<T>) -> Menu<T> {
+        Menu { items, selected: 0 }
+    }
+
+    pub(crate) fn current(&self) -> T {
+        self.items[self.selected]
+    }
+
+    /// Move the selection by `delta` entries, wrapping around at the ends.
+    pub(crate) fn move_selection(&mut self, delta: i32) {
+        let len = self.items.len() as i32;
+        self.selected = (self.selected as i32 + delta).rem_euclid(len) as usize;
+    }
+}
+
+/// Number of hexadecimal digits of a seed.
+pub(crate) const SEED_DIGITS: usize = 8;
+
+/// The seed picked on the seed selection screen, edited one hex digit at a time.
+pub(crate) struct SeedSelection {
+    pub seed: u32,
+    /// Index of the selected digit, starting with the most significant one.
+    pub digit: usize,
+}
+
+impl SeedSelection {
+    pub(crate) fn new(seed: u32) -> SeedSelection {
+        SeedSelection { seed, digit: 0 }
+    }
+
+    /// Move the digit selection by `delta`, wrapping around at the ends.
+    pub(crate) fn move_digit(&mut self, delta: i32) {
+        self.digit = (self.digit as i32 + delta).rem_euclid(SEED_DIGITS as i32) as usize;
+    }
+
+    /// Add `delta` to the selected digit. The other digits do not change.
+    pub(crate) fn change_digit(&mut self, delta: i32) {
+        let shift = (SEED_DIGITS - 1 - self.digit) * 4;
+        let value = ((self.seed >> shift) & 0xf) as i32;
+        let value = (value + delta).rem_euclid(16) as u32;
+        self.seed = (self.seed & !(0xf << shift)) | (value << shift);
+    }
+
+    pub(crate) fn text(&self) -> String {
+        format!("{:08X}", self.seed)
+    }
+}
+
+/// Turn some value that changes at runtime, like the number of frames
+/// spent on the title screen, into a seed for a new sky.
+pub(crate) fn random_seed(entropy: u32) -> u32 {
+    let mut rng = entropy;
+    next_random(&mut rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_wraps_around() {
+        let mut menu = Menu::new(vec![TitleItem::Continue, TitleItem::NewSky]);
+        menu.move_selection(1);
+        assert_eq!(menu.current(), TitleItem::NewSky);
+        menu.move_selection(1);
+        assert_eq!(menu.current(), TitleItem::Continue);
+        menu.move_selection(-1);
+        assert_eq!(menu.current(), TitleItem::NewSky);
+    }
+
+    #[test]
+    fn edits_single_digits() {
+        let mut selection = SeedSelection::new(0x0001_E240);
+        selection.move_digit(-1);
+        assert_eq!(selection.digit, SEED_DIGITS - 1);
+        selection.change_digit(1);
+        assert_eq!(selection.text(), "0001E241");
+
+        selection.move_digit(-4);
+        selection.change_digit(-2);
+        assert_eq!(selection.text(), "000FE241");
+        selection.change_digit(1);
+        assert_eq!(selection.seed, 0x0000_E241);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::game::{ACCESSIBLE_STAR_HIT_RADIUS, STAR_HIT_RADIUS};
+use crate::palette::{Palette, Theme, high_contrast};
+
+/// Highest value of `Settings::scroll_speed`.
+pub(crate) const MAX_SCROLL_SPEED: u8 = 4;
+
+/// Highest value of `Settings::volume`.
+pub(crate) const MAX_VOLUME: u8 = 4;
+
+/// Options that the player can change.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Settings {
+    /// Ignore input while a completed constellation is being animated.
+    pub block_input_during_effects: bool,
+    /// Speed of the camera, from 1 to `MAX_SCROLL_SPEED`. At 2, it moves at the original speed.
+    pub scroll_speed: u8,
+    /// Scroll when the mouse is near the border of the screen.
+    pub edge_scroll: bool,
+    /// Volume of music and sound effects, from 0 (muted) to `MAX_VOLUME`.
+    pub volume: u8,
+    /// The colour theme, or `None` to use the theme of the sky.
+    pub theme: Option<Theme>,
+    /// Mark the stars that the current draft can be linked to.
+    pub show_hints: bool,
+    /// Use a palette with more contrast, tell stars apart by shape, stop
+    /// the animations of lines and make stars easier to hit.
+    pub accessible: bool,
+}
+
+impl Settings {
+    pub(crate) const DEFAULT: Settings = Settings {
+        block_input_during_effects: true,
+        scroll_speed: 2,
+        edge_scroll: true,
+        volume: MAX_VOLUME,
+        theme: None,
+        show_hints: true,
+        accessible: false,
+    };
+
+    /// Whether all values are within their ranges.
+    pub(crate) fn is_valid(&self) -> bool {
+        (1..=MAX_SCROLL_SPEED).contains(&self.scroll_speed) && self.volume <= MAX_VOLUME
+    }
+
+    /// The palette to use for the sky with the given seed.
+    pub(crate) fn palette(&self, seed: u32) -> Palette {
+        let palette = self
+            .theme
+            .unwrap_or_else(|| Theme::from_seed(seed))
+            .palette();
+        if self.accessible {
+            high_contrast(palette)
+        } else {
+            palette
+        }
+    }
+
+    /// Distance from a star at which the pointer still hits it.
+    pub(crate) fn star_hit_radius(&self) -> i32 {
+        if self.accessible {
+            ACCESSIBLE_STAR_HIT_RADIUS
+        } else {
+            STAR_HIT_RADIUS
+        }
+    }
+}
+
+/// Encode a theme setting as a single byte, with 0 for the theme of the sky.
+pub(crate) fn theme_to_u8(theme: Option<Theme>) -> u8 {
+    theme.map_or(0, |theme| {
+        Theme::ALL.iter().position(|t| *t == theme).unwrap() as u8 + 1
+    })
+}
+
+/// Decode a theme setting written by `theme_to_u8`. Out of range values
+/// fall back to the theme of the sky.
+pub(crate) fn theme_from_u8(value: u8) -> Option<Theme> {
+    Theme::ALL.get((value as usize).checked_sub(1)?).copied()
+}
+
+/// The settings that are shown in the pause menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Setting {
+    ScrollSpeed,
+    EdgeScroll,
+    Volume,
+    Theme,
+    Hints,
+    Accessibility,
+    BlockInput,
+}
+
+impl Setting {
+    pub(crate) const ALL: [Setting; 7] = [
+        Setting::ScrollSpeed,
+        Setting::EdgeScroll,
+        Setting::Volume,
+        Setting::Theme,
+        Setting::Hints,
+        Setting::Accessibility,
+        Setting::BlockInput,
+    ];
+
+    /// Change the value of this setting by `delta` steps. Numbers stop at the
+    /// ends of their range, palettes wrap around and switches are toggled.
+    pub(crate) fn change(self, settings: &mut Settings, delta: i32) {
+        let step = |value: u8, min: u8, max: u8| {
+            (value as i32 + delta).clamp(min as i32, max as i32) as u8
+        };
+        match self {
+            Setting::ScrollSpeed => {
+                settings.scroll_speed = step(settings.scroll_speed, 1, MAX_SCROLL_SPEED)
+            }
+            Setting::EdgeScroll => settings.edge_scroll = !settings.edge_scroll,
+            Setting::Volume => settings.volume = step(settings.volume, 0, MAX_VOLUME),
+            Setting::Theme => {
+                // The theme of the sky comes before the named themes.
+                let count = Theme::ALL.len() as i32 + 1;
+                let idx = (theme_to_u8(settings.theme) as i32 + delta).rem_euclid(count);
+                settings.theme = theme_from_u8(idx as u8);
+            }
+            Setting::Hints => settings.show_hints = !settings.show_hints,
+            Setting::Accessibility => settings.accessible = !settings.accessible,
+            Setting::BlockInput => {
+                settings.block_input_during_effects = !settings.block_input_during_effects
+            }
+        }
+    }
+
+    /// Step to the next value of this setting, starting over after the last one.
+    pub(crate) fn cycle(self, settings: &mut Settings) {
+        match self {
+            Setting::ScrollSpeed if settings.scroll_speed == MAX_SCROLL_SPEED => {
+                settings.scroll_speed = 1
+            }
+            Setting::Volume if settings.volume == MAX_VOLUME => settings.volume = 0,
+            _ => self.change(settings, 1),
+        }
+    }
+
+    /// The name and the current value of this setting, as shown in the menu.
+    pub(crate) fn label(self, settings: &Settings) -> String {
+        let on_off = |value: bool| if value { "ON" } else { "OFF" };
+        match self {
+            Setting::ScrollSpeed => format!("SCROLL SPEED: {}", settings.scroll_speed),
+            Setting::EdgeScroll => format!("EDGE SCROLL: {}", on_off(settings.edge_scroll)),
+            Setting::Volume => format!("VOLUME: {}", settings.volume),
+            Setting::Theme => {
+                let name = settings.theme.map_or("SKY", |theme| theme.name());
+                format!("THEME: {name}")
+            }
+            Setting::Hints => format!("HINTS: {}", on_off(settings.show_hints)),
+            Setting::Accessibility => {
+                format!("ACCESSIBLE MODE: {}", on_off(settings.accessible))
+            }
+            Setting::BlockInput => format!(
+                "BLOCK INPUT IN EFFECTS: {}",
+                on_off(settings.block_input_during_effects)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_stay_in_range() {
+        let mut settings = Settings::DEFAULT;
+        for _ in 0..10 {
+            Setting::ScrollSpeed.change(&mut settings, 1);
+            Setting::Volume.change(&mut settings, -1);
+            Setting::Theme.change(&mut settings, -1);
+            assert!(settings.is_valid());
+        }
+        assert_eq!(settings.scroll_speed, MAX_SCROLL_SPEED);
+        assert_eq!(settings.volume, 0);
+
+        Setting::Volume.cycle(&mut settings);
+        assert_eq!(settings.volume, 1);
+        Setting::ScrollSpeed.cycle(&mut settings);
+        assert_eq!(settings.scroll_speed, 1);
+
+        settings.theme = None;
+        Setting::Theme.change(&mut settings, -1);
+        assert_eq!(settings.theme, Some(Theme::ColorBlindSafe));
+        Setting::Theme.change(&mut settings, 1);
+        assert_eq!(settings.theme, None);
+        Setting::Theme.change(&mut settings, 1);
+        assert_eq!(settings.theme, Some(Theme::Night));
+
+        Setting::Hints.change(&mut settings, 1);
+        assert!(!settings.show_hints);
+        assert_eq!(Setting::Hints.label(&settings), "HINTS: OFF");
+
+        Setting::BlockInput.cycle(&mut settings);
+        assert!(!settings.block_input_during_effects);
+        assert_eq!(
+            Setting::BlockInput.label(&settings),
+            "BLOCK INPUT IN EFFECTS: OFF"
+        );
+    }
+}
@@ -2,101 +2,102 @@
 mod sky {
     use super::prerender::SkyPrerender;
     use crate::Aoc;
-    use crate::line::{Link, draw_line};
-    use crate::star::Star;
+    use crate::camera::Camera;
+    use crate::game::Game;
+    use crate::framebuffer::{View, Wasm4Framebuffer};
+    use crate::render::render_sky;
+    use crate::settings::Settings;
+    use crate::util::SkyParams;
     use skylite_core::{ProjectControls, RenderControls};
     use wasm4_target::{MOUSE_X, MOUSE_Y};
 
     pub(crate) struct Sky {
-        scroll_sub_x: f32,
-        scroll_sub_y: f32,
-        #[skylite_proc::nodes]
-        pub stars: Vec<Star>,
-        #[skylite_proc::nodes]
-        pub links: Vec<Link>,
+        /// Moves the screen over the sky, see `pre_update`.
+        pub camera: Camera,
+        pub game: Game,
+        /// Keep the pointer on screen, instead of scrolling with the mouse.
+        pub follow_pointer: bool,
+        /// Whether the camera moves at all. Menus turn this off.
+        pub scrolling: bool,
+        /// The options of the player, which affect the camera, the palette and the hints.
+        pub settings: Settings,
         #[skylite_proc::node]
         prerender: SkyPrerender,
     }
 
     impl Sky {
         #[skylite_proc::new]
-        pub fn new(stars: Vec<Star>, links: Vec<Link>) -> Sky {
+        pub fn new(seed: u32) -> Sky {
+            let game = Game::new(seed, SkyParams::STANDARD);
             Sky {
-                scroll_sub_x: 0.0,
-                scroll_sub_y: 0.0,
-                stars,
-                links,
+                camera: Camera::new(&game.params),
+                follow_pointer: false,
+                scrolling: true,
+                settings: Settings::DEFAULT,
+                game,
                 prerender: SkyPrerender::new(),
             }
         }
 
-        fn update_focus(
-            focus_x: &mut i32,
-            focus_y: &mut i32,
-            scroll_sub_x: &mut f32,
-            scroll_sub_y: &mut f32,
-        ) {
+        /// Create the sky for a game that was already started, e.g. one loaded from disk.
+        pub fn with_game(game: Game) -> Sky {
+            Sky {
+                camera: Camera::new(&game.params),
+                follow_pointer: false,
+                scrolling: true,
+                settings: Settings::DEFAULT,
+                game,
+                prerender: SkyPrerender::new(),
+            }
+        }
+
+        /// How far to scroll when the mouse is near the border of the screen.
+        fn edge_scroll_delta(settings: &Settings) -> (f32, f32) {
             const SCROLL_THRESHOLD_LOW: i16 = 30;
             const MIN_SCROLL_DELTA: f32 = 0.35;
             const MAX_SCROLL_DELTA: f32 = 3.0;
             const SCROLL_THRESHOLD_HIGH: i16 =
                 wasm4_target::SCREEN_SIZE as i16 - SCROLL_THRESHOLD_LOW;
 
-            let mouse_x_raw = unsafe { *MOUSE_X }
-                .min(wasm4_target::SCREEN_SIZE as i16)
-                .max(0);
-            let mouse_y_raw = unsafe { *MOUSE_Y }
-                .min(wasm4_target::SCREEN_SIZE as i16)
-                .max(0);
-
-            let dx = if mouse_x_raw < SCROLL_THRESHOLD_LOW {
-                (mouse_x_raw - SCROLL_THRESHOLD_LOW) as f32 / SCROLL_THRESHOLD_LOW as f32
-                    * (MAX_SCROLL_DELTA - MIN_SCROLL_DELTA)
-                    - MIN_SCROLL_DELTA
-            } else if mouse_x_raw >= SCROLL_THRESHOLD_HIGH {
-                (mouse_x_raw - SCROLL_THRESHOLD_HIGH) as f32 / SCROLL_THRESHOLD_LOW as f32
-                    * (MAX_SCROLL_DELTA - MIN_SCROLL_DELTA)
-                    + MIN_SCROLL_DELTA
-            } else {
-                0.0
-            };
+            if !settings.edge_scroll {
+                return (0.0, 0.0);
+            }
 
-            let dy = if mouse_y_raw < SCROLL_THRESHOLD_LOW {
-                (mouse_y_raw - SCROLL_THRESHOLD_LOW) as f32 / SCROLL_THRESHOLD_LOW as f32
-                    * (MAX_SCROLL_DELTA - MIN_SCROLL_DELTA)
-                    - MIN_SCROLL_DELTA
-            } else if mouse_y_raw >= SCROLL_THRESHOLD_HIGH {
-                (mouse_y_raw - SCROLL_THRESHOLD_HIGH) as f32 / SCROLL_THRESHOLD_LOW as f32
-                    * (MAX_SCROLL_DELTA - MIN_SCROLL_DELTA)
-                    + MIN_SCROLL_DELTA
-            } else {
-                0.0
+            let delta = |pos: i16| {
+                let pos = pos.min(wasm4_target::SCREEN_SIZE as i16).max(0);
+                if pos < SCROLL_THRESHOLD_LOW {
+                    (pos - SCROLL_THRESHOLD_LOW) as f32 / SCROLL_THRESHOLD_LOW as f32
+                        * (MAX_SCROLL_DELTA - MIN_SCROLL_DELTA)
+                        - MIN_SCROLL_DELTA
+                } else if pos >= SCROLL_THRESHOLD_HIGH {
+                    (pos - SCROLL_THRESHOLD_HIGH) as f32 / SCROLL_THRESHOLD_LOW as f32
+                        * (MAX_SCROLL_DELTA - MIN_SCROLL_DELTA)
+                        + MIN_SCROLL_DELTA
+                } else {
+                    0.0
+                }
             };
 
-            let new_focus_x = (*focus_x as f32 + *scroll_sub_x + dx).max(0.0).min(
-                (crate::util::SKY_WIDTH_SECTIONS * crate::util::SECTION_WIDTH
-                    - wasm4_target::SCREEN_SIZE as usize) as f32,
-            );
-            let new_focus_y = (*focus_y as f32 + *scroll_sub_y + dy).max(0.0).min(
-                (crate::util::SKY_HEIGHT_SECTIONS * crate::util::SECTION_HEIGHT
-                    - wasm4_target::SCREEN_SIZE as usize) as f32,
-            );
-            *scroll_sub_x = new_focus_x.fract();
-            *scroll_sub_y = new_focus_y.fract();
-
-            *focus_x = new_focus_x as i32;
-            *focus_y = new_focus_y as i32;
+            let speed = settings.scroll_speed as f32 / 2.0;
+            let (mouse_x, mouse_y) = unsafe { (*MOUSE_X, *MOUSE_Y) };
+            (delta(mouse_x) * speed, delta(mouse_y) * speed)
         }
 
         #[skylite_proc::pre_update]
         fn pre_update(&mut self, controls: &mut ProjectControls<Aoc>) {
-            let (mut focus_x, mut focus_y) = controls.get_focus();
-            Self::update_focus(
-                &mut focus_x,
-                &mut focus_y,
-                &mut self.scroll_sub_x,
-                &mut self.scroll_sub_y,
-            );
+            self.prerender.palette = self.settings.palette(self.game.seed);
+            if !self.scrolling {
+                return;
+            }
+            if self.follow_pointer {
+                let (x, y) = self.game.effective_pointer_pos();
+                self.camera.keep_in_view(x, y);
+            } else if !self.camera.is_dragging() {
+                let (dx, dy) = Self::edge_scroll_delta(&self.settings);
+                self.camera.scroll_by(dx, dy);
+            }
+            self.camera.update();
+            let (focus_x, focus_y) = self.camera.focus();
             controls.set_focus(focus_x, focus_y);
         }
 
@@ -107,11 +108,11 @@ mod sky {
 
         #[skylite_proc::render]
         fn render(&self, ctx: &mut RenderControls<Aoc>) {
-            for link in &self.links {
-                let start = &self.stars[link.start_idx as usize];
-                let end = &self.stars[link.end_idx as usize];
-                draw_line(start.x, start.y, end.x, end.y, link.style, ctx);
-            }
+            let view = View {
+                reduce_motion: self.settings.accessible,
+                ..View::from_controls(ctx)
+            };
+            render_sky(&self.game, &self.settings, &view, &mut Wasm4Framebuffer);
         }
     }
 }
@@ -122,29 +123,30 @@ mod prerender {
     use skylite_core::RenderControls;
 
     use crate::Aoc;
+    use crate::framebuffer::{Framebuffer, Wasm4Framebuffer};
+    use crate::palette::{Palette, Theme};
     // use wasm4_target::{MOUSE_X, MOUSE_Y};
 
-    pub(crate) struct SkyPrerender;
+    pub(crate) struct SkyPrerender {
+        /// Applied to the WASM-4 palette on every frame, see `Settings::palette`.
+        pub palette: Palette,
+    }
 
     impl SkyPrerender {
         #[skylite_proc::new]
         pub(crate) fn new() -> SkyPrerender {
-            SkyPrerender
+            SkyPrerender {
+                palette: Theme::Night.palette(),
+            }
         }
 
         #[skylite_proc::render]
         fn render(&self, _ctx: &mut RenderControls<Aoc>) {
             unsafe {
-                let palette = &mut *wasm4_target::PALETTE;
-                palette[0] = 0x040411;
-                palette[1] = 0x32324B;
-                palette[2] = 0x697B9E;
-                palette[3] = 0xFAFAF0;
+                *wasm4_target::PALETTE = self.palette;
                 *wasm4_target::DRAW_COLORS = 0x4320;
-
-                let frame_buffer = &mut *wasm4_target::FRAMEBUFFER;
-                frame_buffer.as_mut_slice().fill(0);
             }
+            Wasm4Framebuffer.clear();
 
             // let (focus_x, focus_y) = ctx.get_focus();
             // let mouse_x = unsafe { *MOUSE_X } + focus_x as i16;
@@ -0,0 +1,273 @@
+use crate::constellations::Constellations;
+use crate::line::Link;
+use crate::rules::check_link;
+use crate::star::Star;
+use crate::util::{SkyParams, get_stars_within_range};
+
+/// A group of bright stars that are already linked to each other.
+struct Group {
+    /// One of the stars, which identifies the constellation of the group.
+    star_idx: u16,
+    size: usize,
+    /// Neighboring groups, as `(group_idx, star_idx, neighbor_star_idx)`.
+    /// The two stars are close enough to be linked. Collected once the
+    /// search reaches the group, see `Search::neighbors`.
+    neighbors: Option<Vec<(usize, u16, u16)>>,
+}
+
+/// Split the bright stars into groups of linked stars. Returns the groups
+/// and the index of the group of each star, or `usize::MAX` for dim stars.
+fn collect_groups(stars: &[Star], constellations: &Constellations) -> (Vec<Group>, Vec<usize>) {
+    let mut group_of = vec![usize::MAX; stars.len()];
+    let mut groups: Vec<Group> = Vec::new();
+    for (star_idx, star) in stars.iter().enumerate() {
+        if !star.bright || group_of[star_idx] != usize::MAX {
+            continue;
+        }
+
+        let constellation = constellations.stars(star_idx);
+        for idx in constellation {
+            group_of[*idx as usize] = groups.len();
+        }
+        groups.push(Group {
+            star_idx: star_idx as u16,
+            size: constellation.len(),
+            neighbors: None,
+        });
+    }
+    (groups, group_of)
+}
+
+struct Search<'a> {
+    params: &'a SkyParams,
+    sections: &'a [Vec<u16>],
+    stars: &'a [Star],
+    links: &'a [Link],
+    constellations: &'a Constellations,
+    groups: Vec<Group>,
+    group_of: Vec<usize>,
+    threshold: usize,
+    max_groups: usize,
+    max_group_size: usize,
+    /// Groups in the current candidate, with the link that connects each
+    /// group to the candidate. The first group has no link.
+    candidate: Vec<(usize, Option<(u16, u16)>)>,
+    size: usize,
+}
+
+impl Search<'_> {
+    /// The groups that can be linked to `group_idx` without breaking the
+    /// link rules. Checking the rules is expensive, so this is only done
+    /// for the groups that the search actually reaches.
+    fn neighbors(&mut self, group_idx: usize) -> &[(usize, u16, u16)] {
+        if self.groups[group_idx].neighbors.is_none() {
+            let mut neighbors: Vec<(usize, u16, u16)> = Vec::new();
+            let group_star_idx = self.groups[group_idx].star_idx as usize;
+            for star_idx in self.constellations.stars(group_star_idx) {
+                let star = &self.stars[*star_idx as usize];
+                let Some(section_idx) = self.params.section_idx(star.x, star.y) else {
+                    continue;
+                };
+                let Some(idx) = self.sections[section_idx]
+                    .iter()
+                    .position(|idx| idx == star_idx)
+                else {
+                    continue;
+                };
+
+                let reachable = get_stars_within_range(
+                    self.params,
+                    self.sections,
+                    self.stars,
+                    section_idx,
+                    idx,
+                    self.params.star_dist_max_for_line,
+                );
+                for (other_section_idx, other_idx) in reachable {
+                    let other_star_idx = self.sections[other_section_idx][other_idx];
+                    let other_group_idx = self.group_of[other_star_idx as usize];
+                    if other_group_idx != group_idx
+                        && !neighbors.iter().any(|n| n.0 == other_group_idx)
+                        && check_link(
+                            &self.params.link_rules,
+                            self.stars,
+                            self.links,
+                            *star_idx,
+                            other_star_idx,
+                        )
+                        .is_ok()
+                    {
+                        neighbors.push((other_group_idx, *star_idx, other_star_idx));
+                    }
+                }
+            }
+            self.groups[group_idx].neighbors = Some(neighbors);
+        }
+        self.groups[group_idx].neighbors.as_deref().unwrap()
+    }
+
+    fn contains(&self, group_idx: usize) -> bool {
+        self.candidate.iter().any(|(idx, _)| *idx == group_idx)
+    }
+
+    fn is_adjacent(&mut self, group_idx: usize) -> bool {
+        (0..self.candidate.len()).any(|i| {
+            let idx = self.candidate[i].0;
+            self.neighbors(idx).iter().any(|n| n.0 == group_idx)
+        })
+    }
+
+    /// Grow the candidate by every group in `extension`, enumerating each
+    /// connected set of groups only once (ESU algorithm).
+    fn extend(&mut self, root: usize, mut extension: Vec<(usize, u16, u16)>) -> bool {
+        if self.size >= self.threshold {
+            return true;
+        }
+        let groups_left = self.max_groups - self.candidate.len();
+        if groups_left == 0 || self.size + groups_left * self.max_group_size < self.threshold {
+            return false;
+        }
+
+        while let Some((group_idx, from_star, to_star)) = extension.pop() {
+            let mut next_extension = extension.clone();
+            for neighbor in self.neighbors(group_idx).to_vec() {
+                if neighbor.0 > root
+                    && !self.contains(neighbor.0)
+                    && !self.is_adjacent(neighbor.0)
+                    && !next_extension.iter().any(|n| n.0 == neighbor.0)
+                {
+                    next_extension.push(neighbor);
+                }
+            }
+
+            self.candidate.push((group_idx, Some((from_star, to_star))));
+            self.size += self.groups[group_idx].size;
+            if self.extend(root, next_extension) {
+                return true;
+            }
+            self.size -= self.groups[group_idx].size;
+            self.candidate.pop();
+        }
+        false
+    }
+}
+
+/// Find a constellation that can still be completed with the given amount
+/// of light. Returns the links that have to be added, or `None` if no
+/// constellation of `constellation_threshold` stars can be completed anymore.
+///
+/// Each link costs one light, and light is only refunded once the
+/// constellation is complete, so at most `light` links can be added.
+/// The links are checked against the rules for existing links, but not
+/// against each other.
+pub(crate) fn find_completable_constellation(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
+    stars: &[Star],
+    links: &[Link],
+    constellations: &Constellations,
+    light: u8,
+) -> Option<Vec<(u16, u16)>> {
+    if light == 0 {
+        return None;
+    }
+
+    let (groups, group_of) = collect_groups(stars, constellations);
+    let max_group_size = groups.iter().map(|g| g.size).max().unwrap_or(0);
+    let mut search = Search {
+        params,
+        sections,
+        stars,
+        links,
+        constellations,
+        groups,
+        group_of,
+        threshold: params.constellation_threshold,
+        max_groups: (light as usize + 1).min(params.constellation_threshold),
+        max_group_size,
+        candidate: Vec::new(),
+        size: 0,
+    };
+
+    for root in 0..search.groups.len() {
+        search.candidate = vec![(root, None)];
+        search.size = search.groups[root].size;
+        let extension = search
+            .neighbors(root)
+            .iter()
+            .filter(|n| n.0 > root)
+            .copied()
+            .collect();
+        if search.extend(root, extension) {
+            return Some(
+                search
+                    .candidate
+                    .iter()
+                    .filter_map(|(_, link)| *link)
+                    .collect(),
+            );
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::STYLE_BRIGHT;
+    use crate::util::generate_sky;
+
+    fn solve(params: &SkyParams, stars: &[Star], light: u8) -> Option<Vec<(u16, u16)>> {
+        let (sections, _, links) = generate_sky(params, 123456);
+        let constellations = Constellations::new(stars.len(), &links);
+        find_completable_constellation(params, &sections, stars, &links, &constellations, light)
+    }
+
+    fn star_count(plan: &[(u16, u16)], star_count: usize, existing: &[Link]) -> usize {
+        let mut all = existing.to_vec();
+        all.extend(plan.iter().map(|(a, b)| Link::new(*a, *b, STYLE_BRIGHT)));
+        Constellations::new(star_count, &all).size(plan[0].0 as usize)
+    }
+
+    #[test]
+    fn finds_constellation_in_fresh_sky() {
+        let params = SkyParams::STANDARD;
+        let (_, stars, links) = generate_sky(&params, 123456);
+
+        let plan = solve(&params, &stars, 8).unwrap();
+        assert!(plan.len() <= 8);
+        assert!(star_count(&plan, stars.len(), &links) >= params.constellation_threshold);
+        for (a, b) in &plan {
+            let (a, b) = (&stars[*a as usize], &stars[*b as usize]);
+            let dx = (a.x - b.x) as i32;
+            let dy = (a.y - b.y) as i32;
+            assert!(dx * dx + dy * dy <= (params.star_dist_max_for_line.pow(2)) as i32);
+        }
+    }
+
+    #[test]
+    fn respects_light() {
+        let params = SkyParams::STANDARD;
+        let (_, stars, links) = generate_sky(&params, 123456);
+
+        assert!(solve(&params, &stars, 0).is_none());
+        // Preset constellations contain at most 3 stars, so a single link
+        // can never complete a constellation of 8.
+        assert!(solve(&params, &stars, 1).is_none());
+        if let Some(plan) = solve(&params, &stars, 3) {
+            assert!(plan.len() <= 3);
+            assert!(star_count(&plan, stars.len(), &links) >= params.constellation_threshold);
+        }
+    }
+
+    #[test]
+    fn ignores_dim_stars() {
+        let params = SkyParams::STANDARD;
+        let (_, mut stars, _) = generate_sky(&params, 123456);
+        for star in stars.iter_mut() {
+            star.bright = false;
+        }
+
+        assert!(solve(&params, &stars, 8).is_none());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::settings::MAX_VOLUME;
+
+/// Channels of the WASM-4 APU, as used in the flags of `tone`.
+pub(crate) const CHANNEL_PULSE1: u8 = 0;
+pub(crate) const CHANNEL_PULSE2: u8 = 1;
+pub(crate) const CHANNEL_TRIANGLE: u8 = 2;
+pub(crate) const CHANNEL_NOISE: u8 = 3;
+
+/// A single note, played with the WASM-4 `tone` function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Tone {
+    /// Frequency at the start and the end of the note, in Hz.
+    pub freq_start: u16,
+    pub freq_end: u16,
+    /// Sustain and release time, in frames.
+    pub sustain: u8,
+    pub release: u8,
+    /// Volume from 0 to 100.
+    pub volume: u8,
+    /// One of the `CHANNEL_*` constants.
+    pub channel: u8,
+}
+
+impl Tone {
+    const fn new(
+        freq_start: u16,
+        freq_end: u16,
+        sustain: u8,
+        release: u8,
+        volume: u8,
+        channel: u8,
+    ) -> Tone {
+        Tone {
+            freq_start,
+            freq_end,
+            sustain,
+            release,
+            volume,
+            channel,
+        }
+    }
+
+    /// This tone at the volume level from the settings.
+    pub(crate) fn scaled(&self, volume: u8) -> Tone {
+        Tone {
+            volume: (self.volume as u32 * volume as u32 / MAX_VOLUME as u32) as u8,
+            ..*self
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn play(&self) {
+        wasm4_target::tone(
+            self.freq_start as u32 | (self.freq_end as u32) << 16,
+            self.sustain as u32 | (self.release as u32) << 8,
+            self.volume as u32,
+            self.channel as u32,
+        );
+    }
+}
+
+// The notes of each effect, with their delay in frames. Effects only use the
+// second pulse channel and the noise channel, so that the bass and the melody
+// of the music keep playing. The counter melody pauses, see `is_playing`.
+const DRAFT_STARTED: &[(u32, Tone)] = &[(0, Tone::new(440, 660, 4, 4, 20, CHANNEL_PULSE2))];
+const SNAP: &[(u32, Tone)] = &[(0, Tone::new(880, 880, 1, 2, 12, CHANNEL_PULSE2))];
+// Short falling tone.
+const DRAFT_CANCELLED: &[(u32, Tone)] = &[(0, Tone::new(440, 220, 8, 0, 25, CHANNEL_PULSE2))];
+const LINK_ADDED: &[(u32, Tone)] = &[(0, Tone::new(523, 784, 4, 8, 40, CHANNEL_PULSE2))];
+const LINK_REJECTED: &[(u32, Tone)] = &[
+    (0, Tone::new(180, 140, 6, 6, 25, CHANNEL_PULSE2)),
+    (0, Tone::new(200, 200, 2, 4, 15, CHANNEL_NOISE)),
+];
+// Rising arpeggio in C major.
+const CONSTELLATION_COMPLETED: &[(u32, Tone)] = &[
+    (0, Tone::new(523, 523, 4, 10, 30, CHANNEL_PULSE2)),
+    (6, Tone::new(659, 659, 4, 10, 30, CHANNEL_PULSE2)),
+    (12, Tone::new(784, 784, 4, 10, 30, CHANNEL_PULSE2)),
+    (18, Tone::new(1047, 1047, 6, 20, 30, CHANNEL_PULSE2)),
+];
+const OUT_OF_LIGHT: &[(u32, Tone)] = &[
+    (0, Tone::new(392, 392, 8, 12, 40, CHANNEL_PULSE2)),
+    (12, Tone::new(330, 330, 8, 12, 40, CHANNEL_PULSE2)),
+    (24, Tone::new(262, 196, 12, 30, 40, CHANNEL_PULSE2)),
+];
+
+/// The sound effects of the game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Effect {
+    DraftStarted,
+    /// The pointer of a draft moved onto a star.
+    Snap,
+    DraftCancelled,
+    LinkAdded,
+    LinkRejected,
+    ConstellationCompleted,
+    OutOfLight,
+}
+
+impl Effect {
+    fn notes(self) -> &'static [(u32, Tone)] {
+        match self {
+            Effect::DraftStarted => DRAFT_STARTED,
+            Effect::Snap => SNAP,
+            Effect::DraftCancelled => DRAFT_CANCELLED,
+            Effect::LinkAdded => LINK_ADDED,
+            Effect::LinkRejected => LINK_REJECTED,
+            Effect::ConstellationCompleted => CONSTELLATION_COMPLETED,
+            Effect::OutOfLight => OUT_OF_LIGHT,
+        }
+    }
+}
+
+/// Plays effects, spreading their notes over the following frames.
+pub(crate) struct SoundPlayer {
+    frame: u32,
+    /// Notes that are still waiting to be played, with the frame they are due.
+    queue: Vec<(u32, Tone)>,
+    /// The frame after which the last note on each channel has faded out.
+    busy_until: [u32; 4],
+}
+
+impl SoundPlayer {
+    pub(crate) fn new() -> SoundPlayer {
+        SoundPlayer {
+            frame: 0,
+            queue: Vec::new(),
+            busy_until: [0; 4],
+        }
+    }
+
+    pub(crate) fn play(&mut self, effect: Effect) {
+        for (delay, tone) in effect.notes() {
+            self.queue.push((self.frame + delay, *tone));
+        }
+    }
+
+    /// Advance by one frame and pass the notes which are due to `output`.
+    pub(crate) fn update(&mut self, mut output: impl FnMut(&Tone)) {
+        let frame = self.frame;
+        self.queue.retain(|(due, tone)| {
+            if *due <= frame {
+                output(tone);
+                self.busy_until[tone.channel as usize] =
+                    frame + tone.sustain as u32 + tone.release as u32;
+                false
+            } else {
+                true
+            }
+        });
+        self.frame += 1;
+    }
+
+    /// Whether an effect is using `channel`, either because one of its notes
+    /// is still sounding or because more of them are due. Music on the same
+    /// channel would cut the effect off, so it should stay silent meanwhile.
+    pub(crate) fn is_playing(&self, channel: u8) -> bool {
+        self.frame < self.busy_until[channel as usize]
+            || self.queue.iter().any(|(_, tone)| tone.channel == channel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_notes_after_their_delay() {
+        let mut player = SoundPlayer::new();
+        player.play(Effect::ConstellationCompleted);
+
+        let mut played = Vec::new();
+        for frame in 0..30 {
+            player.update(|tone| played.push((frame, tone.freq_start)));
+        }
+        assert_eq!(played, vec![(0, 523), (6, 659), (12, 784), (18, 1047)]);
+    }
+
+    #[test]
+    fn effects_only_occupy_one_music_channel() {
+        let effects = [
+            Effect::DraftStarted,
+            Effect::Snap,
+            Effect::DraftCancelled,
+            Effect::LinkAdded,
+            Effect::LinkRejected,
+            Effect::ConstellationCompleted,
+            Effect::OutOfLight,
+        ];
+        for effect in effects {
+            assert!(
+                effect
+                    .notes()
+                    .iter()
+                    .all(|(_, tone)| [CHANNEL_PULSE2, CHANNEL_NOISE].contains(&tone.channel)),
+                "{effect:?}"
+            );
+        }
+
+        let mut player = SoundPlayer::new();
+        player.play(Effect::ConstellationCompleted);
+        player.update(|_| {});
+        assert!(player.is_playing(CHANNEL_PULSE2));
+        assert!(!player.is_playing(CHANNEL_PULSE1));
+        assert!(!player.is_playing(CHANNEL_TRIANGLE));
+        for _ in 0..60 {
+            player.update(|_| {});
+        }
+        assert!(!player.is_playing(CHANNEL_PULSE2));
+    }
+
+    #[test]
+    fn scales_volume() {
+        let (_, tone) = LINK_ADDED[0];
+        assert_eq!(tone.scaled(MAX_VOLUME), tone);
+        assert_eq!(tone.scaled(MAX_VOLUME / 2).volume, tone.volume / 2);
+        assert_eq!(tone.scaled(0).volume, 0);
+    }
+}
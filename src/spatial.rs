@@ -0,0 +1,148 @@
+use crate::star::Star;
+use crate::util::SkyParams;
+
+/// The stars of a sky, sorted into the same grid of sections that
+/// `generate_sky` uses. Stars never move, so the grid is built once
+/// and then used for all lookups by position.
+pub(crate) struct SpatialIndex {
+    width_sections: usize,
+    height_sections: usize,
+    section_width: usize,
+    section_height: usize,
+    pub sections: Vec<Vec<u16>>,
+}
+
+impl SpatialIndex {
+    pub(crate) fn new(params: &SkyParams, sections: Vec<Vec<u16>>) -> SpatialIndex {
+        SpatialIndex {
+            width_sections: params.width_sections,
+            height_sections: params.height_sections,
+            section_width: params.section_width,
+            section_height: params.section_height,
+            sections,
+        }
+    }
+
+    /// Section coordinates of `(x, y)`, clamped to the grid.
+    fn section_pos(&self, x: i32, y: i32) -> (usize, usize) {
+        let section_x = (x.max(0) as usize / self.section_width).min(self.width_sections - 1);
+        let section_y = (y.max(0) as usize / self.section_height).min(self.height_sections - 1);
+        (section_x, section_y)
+    }
+
+    /// Indices of the stars in all sections that overlap the rectangle
+    /// from `(x1, y1)` to `(x2, y2)`, inclusive. This may include stars
+    /// outside of the rectangle.
+    pub(crate) fn stars_in_rect(
+        &self,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let (min_x, min_y) = self.section_pos(x1, y1);
+        let (max_x, max_y) = self.section_pos(x2, y2);
+        (min_y..=max_y).flat_map(move |section_y| {
+            (min_x..=max_x).flat_map(move |section_x| {
+                self.sections[section_y * self.width_sections + section_x]
+                    .iter()
+                    .map(|idx| *idx as usize)
+            })
+        })
+    }
+
+    /// Indices of the stars whose distance to `(x, y)` is at most `radius`.
+    pub(crate) fn stars_within<'a>(
+        &'a self,
+        stars: &'a [Star],
+        x: i16,
+        y: i16,
+        radius: i32,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let (x, y) = (x as i32, y as i32);
+        self.stars_in_rect(x - radius, y - radius, x + radius, y + radius)
+            .filter(move |idx| {
+                let dx = stars[*idx].x as i32 - x;
+                let dy = stars[*idx].y as i32 - y;
+                dx * dx + dy * dy <= radius * radius
+            })
+    }
+
+    /// Indices of the stars in the sections at Chebyshev distance `ring`
+    /// from the section containing `(x, y)`. Together with `min_ring_distance`,
+    /// this allows searching for stars from the nearest sections outwards.
+    pub(crate) fn stars_in_ring(&self, x: i16, y: i16, ring: usize) -> Vec<usize> {
+        let (center_x, center_y) = self.section_pos(x as i32, y as i32);
+        let mut out = Vec::new();
+        let ring = ring as isize;
+        for offset_y in -ring..=ring {
+            for offset_x in -ring..=ring {
+                if offset_x.abs() != ring && offset_y.abs() != ring {
+                    continue;
+                }
+                let section_x = center_x as isize + offset_x;
+                let section_y = center_y as isize + offset_y;
+                if section_x < 0
+                    || section_y < 0
+                    || section_x >= self.width_sections as isize
+                    || section_y >= self.height_sections as isize
+                {
+                    continue;
+                }
+                let section_idx = section_y as usize * self.width_sections + section_x as usize;
+                out.extend(self.sections[section_idx].iter().map(|idx| *idx as usize));
+            }
+        }
+        out
+    }
+
+    /// A lower bound for the distance between a point and any star
+    /// returned by `stars_in_ring` for that point.
+    pub(crate) fn min_ring_distance(&self, ring: usize) -> i32 {
+        (ring.saturating_sub(1) * self.section_width.min(self.section_height)) as i32
+    }
+
+    /// Number of rings needed to cover the whole grid from any section.
+    pub(crate) fn ring_count(&self) -> usize {
+        self.width_sections.max(self.height_sections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::generate_sky;
+
+    #[test]
+    fn finds_same_stars_as_linear_scan() {
+        let params = SkyParams::STANDARD;
+        let (sections, stars, _) = generate_sky(&params, 123456);
+        let index = SpatialIndex::new(&params, sections);
+
+        for (x, y, radius) in [(0, 0, 40), (320, 320, 35), (639, 100, 60), (-20, 700, 50)] {
+            let mut found: Vec<_> = index.stars_within(&stars, x, y, radius).collect();
+            found.sort();
+            let expected: Vec<_> = (0..stars.len())
+                .filter(|idx| {
+                    let dx = (stars[*idx].x - x) as i32;
+                    let dy = (stars[*idx].y - y) as i32;
+                    dx * dx + dy * dy <= radius * radius
+                })
+                .collect();
+            assert_eq!(found, expected, "Around ({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn rings_cover_all_stars() {
+        let params = SkyParams::STANDARD;
+        let (sections, stars, _) = generate_sky(&params, 123456);
+        let index = SpatialIndex::new(&params, sections);
+
+        let mut found: Vec<_> = (0..index.ring_count())
+            .flat_map(|ring| index.stars_in_ring(200, 500, ring))
+            .collect();
+        found.sort();
+        assert_eq!(found, (0..stars.len()).collect::<Vec<_>>());
+    }
+}
@@ -1,68 +1,226 @@
-#[skylite_proc::node_definition("./project/project.scm", "star")]
-mod x {
-    use skylite_core::SkyliteTarget;
-    use skylite_core::RenderControls;
+use crate::framebuffer::{Framebuffer, View};
 
-    use crate::Aoc;
+/// How bright a star appears, independent of whether it was used up already.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Magnitude {
+    Faint,
+    Medium,
+    Brilliant,
+}
 
-    pub(crate) struct Star {
-        pub x: i16,
-        pub y: i16,
-        pub bright: bool,
+impl Magnitude {
+    /// Pick a magnitude from a random number. Like in the real sky, each
+    /// class is about three times as common as the next brighter one.
+    pub(crate) fn from_random(value: u32) -> Magnitude {
+        match value % 13 {
+            0 => Magnitude::Brilliant,
+            1..=3 => Magnitude::Medium,
+            _ => Magnitude::Faint,
+        }
     }
 
-    static STAR_GRAPHIC_DIM: &[u8] = &[0b0001_0001, 0b1001_0001, 0b0000_0000, 3, 0];
-
-    static STAR_GRAPHIC_BRIGHT: &[u8] = &[
-        0b0000_0100,
-        0b0000_0010,
-        0b0000_0110,
-        0b1110_0100,
-        0b0010_0000,
-        0b0000_0100,
-        0b0000_0000,
-        5,
-        0,
-    ];
-
-    impl Star {
-        #[skylite_proc::new]
-        pub(crate) fn new(x: i16, y: i16, bright: bool) -> Star {
-            Star { x, y, bright }
+    /// Extra light that a star refunds when its constellation is completed.
+    pub(crate) fn light_bonus(self) -> u8 {
+        match self {
+            Magnitude::Faint | Magnitude::Medium => 0,
+            Magnitude::Brilliant => 1,
         }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Star {
+    pub x: i16,
+    pub y: i16,
+    pub bright: bool,
+    pub magnitude: Magnitude,
+    /// Offset of the twinkle animation, so that stars do not twinkle in sync.
+    pub twinkle_phase: u8,
+}
+
+/// Every star twinkles once every `TWINKLE_PERIOD` frames,
+/// for `TWINKLE_FRAMES` frames.
+const TWINKLE_PERIOD: u32 = 256;
+const TWINKLE_FRAMES: u32 = 6;
+
+static STAR_GRAPHIC_DIM: &[u8] = &[0b0001_0001, 0b1001_0001, 0b0000_0000, 3, 0];
+
+static STAR_GRAPHIC_FAINT: &[u8] = &[
+    0b0000_0100,
+    0b0000_0010,
+    0b0000_0110,
+    0b1110_0100,
+    0b0010_0000,
+    0b0000_0100,
+    0b0000_0000,
+    5,
+    0,
+];
+
+static STAR_GRAPHIC_FAINT_TWINKLE: &[u8] = &[
+    0b0100_0000,
+    0b0100_1000,
+    0b1000_0000,
+    0b1100_0000,
+    0b1000_1000,
+    0b0100_0000,
+    0b0100_0000,
+    5,
+    0,
+];
+
+static STAR_GRAPHIC_MEDIUM: &[u8] = &[
+    0b0000_0001,
+    0b0000_0000,
+    0b0000_1000,
+    0b0000_0000,
+    0b0110_0100,
+    0b0001_1010,
+    0b1110_1001,
+    0b0000_0110,
+    0b0100_0000,
+    0b0000_1000,
+    0b0000_0000,
+    0b0001_0000,
+    0b0000_0000,
+    7,
+    0,
+];
+
+static STAR_GRAPHIC_MEDIUM_TWINKLE: &[u8] = &[
+    0b0100_0000,
+    0b0000_0100,
+    0b1000_0000,
+    0b1000_0000,
+    0b1001_1000,
+    0b0000_0001,
+    0b1101_0000,
+    0b0000_1001,
+    0b1000_0000,
+    0b1000_0000,
+    0b1000_0100,
+    0b0000_0000,
+    0b0100_0000,
+    7,
+    0,
+];
+
+static STAR_GRAPHIC_BRILLIANT: &[u8] = &[
+    0b0000_0000,
+    0b0100_0000,
+    0b0000_0000,
+    0b0001_0000,
+    0b0000_0000,
+    0b0000_1000,
+    0b0000_0000,
+    0b0000_0110,
+    0b0100_0000,
+    0b0101_1010,
+    0b1110_1001,
+    0b0100_0000,
+    0b0110_0100,
+    0b0000_0000,
+    0b0000_1000,
+    0b0000_0000,
+    0b0000_0001,
+    0b0000_0000,
+    0b0000_0000,
+    0b0100_0000,
+    0b0000_0000,
+    9,
+    0,
+];
+
+static STAR_GRAPHIC_BRILLIANT_TWINKLE: &[u8] = &[
+    0b0100_0000,
+    0b0000_0000,
+    0b0100_0100,
+    0b0000_0000,
+    0b0100_0000,
+    0b1000_0000,
+    0b1000_0000,
+    0b0000_1010,
+    0b1000_0000,
+    0b0000_0010,
+    0b1110_0000,
+    0b0000_0000,
+    0b1010_1000,
+    0b0000_0000,
+    0b1000_0000,
+    0b1000_0000,
+    0b0100_0000,
+    0b0000_0100,
+    0b0100_0000,
+    0b0000_0000,
+    0b0100_0000,
+    9,
+    0,
+];
 
-        #[skylite_proc::render]
-        fn render(&self, ctx: &mut RenderControls<Aoc>) {
-            let (focus_x, focus_y) = ctx.get_focus();
-            if self.bright {
-                ctx.get_target_instance_mut().draw_sub(
-                    STAR_GRAPHIC_BRIGHT,
-                    self.x - 2 - focus_x as i16,
-                    self.y - 2 - focus_y as i16,
-                    0,
-                    0,
-                    5,
-                    5,
-                    false,
-                    false,
-                    false,
-                );
-            } else {
-                ctx.get_target_instance_mut().draw_sub(
-                    STAR_GRAPHIC_DIM,
-                    self.x - 1 - focus_x as i16,
-                    self.y - 1 - focus_y as i16,
-                    0,
-                    0,
-                    3,
-                    3,
-                    false,
-                    false,
-                    false,
-                );
-            }
+impl Star {
+    pub(crate) fn new(x: i16, y: i16, bright: bool) -> Star {
+        Star {
+            x,
+            y,
+            bright,
+            magnitude: Magnitude::Faint,
+            twinkle_phase: 0,
         }
     }
-}
 
-pub(crate) use x::*;
+    pub(crate) fn render(&self, view: &View, fb: &mut impl Framebuffer) {
+        let x = self.x as i32 - view.focus_x;
+        let y = self.y as i32 - view.focus_y;
+        if !self.bright {
+            fb.blit_2bpp(STAR_GRAPHIC_DIM, x - 1, y - 1, 3, 3);
+            return;
+        }
+
+        // While twinkling, the rays of a star briefly turn diagonal.
+        let twinkling = (view.frame + self.twinkle_phase as u32) % TWINKLE_PERIOD < TWINKLE_FRAMES;
+        let (sprite, twinkle_sprite, size) = match self.magnitude {
+            Magnitude::Faint => (STAR_GRAPHIC_FAINT, STAR_GRAPHIC_FAINT_TWINKLE, 5),
+            Magnitude::Medium => (STAR_GRAPHIC_MEDIUM, STAR_GRAPHIC_MEDIUM_TWINKLE, 7),
+            Magnitude::Brilliant => (STAR_GRAPHIC_BRILLIANT, STAR_GRAPHIC_BRILLIANT_TWINKLE, 9),
+        };
+        let sprite = if twinkling { twinkle_sprite } else { sprite };
+        let offset = size as i32 / 2;
+        fb.blit_2bpp(sprite, x - offset, y - offset, size, size);
+    }
+
+    /// Draw the star in the accessible style, which tells stars apart by shape
+    /// instead of brightness: Stars that can still be linked are crosses, with
+    /// longer arms for brighter stars, and used up stars are single dots.
+    pub(crate) fn render_shape(&self, view: &View, fb: &mut impl Framebuffer) {
+        let x = self.x as i32 - view.focus_x;
+        let y = self.y as i32 - view.focus_y;
+        if !self.bright {
+            fb.set_pixel(x, y, 1);
+            return;
+        }
+
+        let arm = match self.magnitude {
+            Magnitude::Faint => 1,
+            Magnitude::Medium => 2,
+            Magnitude::Brilliant => 3,
+        };
+        fb.fill_rect(x - arm, y, 2 * arm as u32 + 1, 1, 3);
+        fb.fill_rect(x, y - arm, 1, 2 * arm as u32 + 1, 3);
+    }
+
+    /// Draw a pulsing ring of dots around a star that the current draft can be linked to.
+    pub(crate) fn render_target_pulse(&self, view: &View, fb: &mut impl Framebuffer) {
+        const PULSE_STEP_FRAMES: u32 = 8;
+
+        let x = self.x as i32 - view.focus_x;
+        let y = self.y as i32 - view.focus_y;
+        let radius = if view.reduce_motion {
+            4
+        } else {
+            3 + (view.frame / PULSE_STEP_FRAMES % 3) as i32
+        };
+        for (dx, dy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+            fb.set_pixel(x + dx * radius, y + dy * radius, 1);
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use super::star::Star;
+use super::star::{Magnitude, Star};
+use crate::constellations::Constellations;
 use crate::line::{Link, STYLE_BRIGHT, STYLE_DIM};
+use crate::rules::LinkRules;
 
 pub(crate) fn next_random(state: &mut u32) -> u32 {
     *state = ((*state as u64 * 134775813 + 1) & 0xffff_ffff) as u32;
@@ -9,46 +11,183 @@ pub(crate) fn next_random(state: &mut u32) -> u32 {
     out
 }
 
-pub const SKY_WIDTH_SECTIONS: usize = 10;
-pub const SKY_HEIGHT_SECTIONS: usize = 10;
-pub const SECTION_WIDTH: usize = 64;
-pub const SECTION_HEIGHT: usize = 64;
+const MAX_ADJUSTMENTS_PER_STAR: usize = 50;
 
-const MAX_STARS: usize = 350;
+/// Placing a star fails when all adjustments end up out of bounds or too close
+/// to other stars. Give up on filling the sky after this many failures per star,
+/// so that parameters that leave no room for `max_stars` cannot hang the game.
+const MAX_ATTEMPTS_PER_STAR: usize = 100;
 
-/// Minimum distance between two stars.
-const STAR_DIST_MIN: usize = 20;
+/// Parameters that control the size and layout of a generated sky,
+/// and the rules for playing on it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SkyParams {
+    pub width_sections: usize,
+    pub height_sections: usize,
 
-/// If the distance between a pair of stars is within
-/// `STAR_DIST_MIN < d < STAR_DIST_MAX_FOR_PRESET_LINE`,
-/// the pair may be pre-connected.
-const STAR_DIST_MAX_FOR_PRESET_LINE: usize = 30;
+    /// Size of a section in pixels. Stars only look at the neighboring
+    /// sections when checking distances, so this must not be smaller than
+    /// `star_dist_dead_zone_end`.
+    pub section_width: usize,
+    pub section_height: usize,
 
-/// The maximum distance between a pair of stars that
-/// allows a line being drawn between them.
-pub(crate) const STAR_DIST_MAX_FOR_LINE: usize = 35;
+    pub max_stars: usize,
 
-/// No pair of stars must have a distance that is within
-/// `STAR_DIST_MAX_FOR_LINE < d < STAR_DIST_DEAD_ZONE_END`.
-/// This is so that it is more visually obvious whether a line
-/// can be drawn between two stars.
-const STAR_DIST_DEAD_ZONE_END: usize = 47;
+    /// Minimum distance between two stars.
+    pub star_dist_min: usize,
 
-const MAX_ADJUSTMENTS_PER_STAR: usize = 50;
+    /// If the distance between a pair of stars is within
+    /// `star_dist_min < d < star_dist_max_for_preset_line`,
+    /// the pair may be pre-connected.
+    pub star_dist_max_for_preset_line: usize,
+
+    /// The maximum distance between a pair of stars that
+    /// allows a line being drawn between them.
+    pub star_dist_max_for_line: usize,
+
+    /// No pair of stars must have a distance that is within
+    /// `star_dist_max_for_line < d < star_dist_dead_zone_end`.
+    /// This is so that it is more visually obvious whether a line
+    /// can be drawn between two stars.
+    pub star_dist_dead_zone_end: usize,
+
+    /// Chance for a preset line to be generated between two stars,
+    /// if the distance between them is less than `star_dist_max_for_preset_line`.
+    pub preset_line_chance: f32,
+
+    /// The maximum number of stars that can be pre-connected.
+    pub max_preset_constellation_size: usize,
+
+    /// When a constellation exceeds this many stars, it will be locked in.
+    pub constellation_threshold: usize,
+
+    /// Which links the player may add.
+    pub link_rules: LinkRules,
+}
+
+impl SkyParams {
+    pub(crate) const STANDARD: SkyParams = SkyParams {
+        width_sections: 10,
+        height_sections: 10,
+        section_width: 64,
+        section_height: 64,
+        max_stars: 350,
+        star_dist_min: 20,
+        star_dist_max_for_preset_line: 30,
+        star_dist_max_for_line: 35,
+        star_dist_dead_zone_end: 47,
+        preset_line_chance: 0.4,
+        max_preset_constellation_size: 3,
+        constellation_threshold: 8,
+        link_rules: LinkRules::STRICT,
+    };
+
+    /// A small sky with short constellations, for learning the game.
+    #[cfg(test)]
+    pub(crate) const TUTORIAL: SkyParams = SkyParams {
+        width_sections: 4,
+        height_sections: 4,
+        max_stars: 50,
+        preset_line_chance: 0.2,
+        max_preset_constellation_size: 2,
+        constellation_threshold: 5,
+        link_rules: LinkRules::RELAXED,
+        ..SkyParams::STANDARD
+    };
+
+    /// A crowded sky with shorter lines and more preset lines to work around.
+    #[cfg(test)]
+    pub(crate) const DENSE: SkyParams = SkyParams {
+        section_width: 48,
+        section_height: 48,
+        max_stars: 300,
+        star_dist_min: 16,
+        star_dist_max_for_preset_line: 24,
+        star_dist_max_for_line: 28,
+        star_dist_dead_zone_end: 38,
+        preset_line_chance: 0.6,
+        constellation_threshold: 10,
+        ..SkyParams::STANDARD
+    };
+
+    /// A large sky for exploring.
+    #[cfg(test)]
+    pub(crate) const HUGE: SkyParams = SkyParams {
+        width_sections: 30,
+        height_sections: 30,
+        max_stars: 3150,
+        ..SkyParams::STANDARD
+    };
+
+    /// Check the assumptions that `generate_sky`, the save format and the rest
+    /// of the game make about the parameters.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.width_sections > 0
+            && self.height_sections > 0
+            && self.width_sections <= u8::MAX as usize
+            && self.height_sections <= u8::MAX as usize
+            && self.section_width <= u8::MAX as usize
+            && self.section_height <= u8::MAX as usize
+            && self.width() <= i16::MAX as usize
+            && self.height() <= i16::MAX as usize
+            && self.section_width >= self.star_dist_dead_zone_end
+            && self.section_height >= self.star_dist_dead_zone_end
+            && self.max_stars > 0
+            && self.max_stars <= u16::MAX as usize
+            && self.star_dist_min > 0
+            && self.star_dist_min <= self.star_dist_max_for_preset_line
+            && self.star_dist_max_for_preset_line <= self.star_dist_max_for_line
+            && self.star_dist_max_for_line < self.star_dist_dead_zone_end
+            && self.star_dist_dead_zone_end <= u8::MAX as usize
+            // Stars keep `star_dist_min` apart, so each needs about that much
+            // room in both directions.
+            && self.max_stars <= self.width() * self.height() / self.star_dist_min.pow(2)
+            && (0.0..=1.0).contains(&self.preset_line_chance)
+            && self.max_preset_constellation_size <= u8::MAX as usize
+            && self.constellation_threshold >= 4
+            && self.constellation_threshold <= u8::MAX as usize
+    }
 
-/// Chance for a preset line to be generated between two stars,
-/// if the distance between them is less than `STAR_DIST_MAX_FOR_PRESET_LINE`.
-const PRESET_LINE_CHANCE: f32 = 0.4;
+    /// Width of the sky in pixels.
+    pub(crate) fn width(&self) -> usize {
+        self.width_sections * self.section_width
+    }
 
-/// The maximum number of stars that can be pre-connected.
-const MAX_PRESET_CONSTELLATION_SIZE: usize = 3;
+    /// Height of the sky in pixels.
+    pub(crate) fn height(&self) -> usize {
+        self.height_sections * self.section_height
+    }
 
-/// When a constellation exceeds this many stars, it will be locked in.
-pub(crate) const CONSTELLATION_THRESHOLD: usize = 8;
+    pub(crate) fn is_in_bounds(&self, x: i16, y: i16) -> bool {
+        x >= 0 && (x as usize) < self.width() && y >= 0 && (y as usize) < self.height()
+    }
 
-fn neighboring_section_indices(section_idx: usize) -> impl Iterator<Item = usize> {
-    let section_x = section_idx % SKY_WIDTH_SECTIONS;
-    let section_y = section_idx / SKY_WIDTH_SECTIONS;
+    /// Index of the section containing `(x, y)`, or `None` if the point is outside of the sky.
+    pub(crate) fn section_idx(&self, x: i16, y: i16) -> Option<usize> {
+        if !self.is_in_bounds(x, y) {
+            return None;
+        }
+        let section_x = x as usize / self.section_width;
+        let section_y = y as usize / self.section_height;
+        Some(section_y * self.width_sections + section_x)
+    }
+}
+
+/// Distance between two stars, as used for the distance rules above.
+pub(crate) fn star_distance(dx: i32, dy: i32) -> i32 {
+    // Add 1 here, to avoid allowing too large distances,
+    // because isqrt rounds down.
+    (dx * dx + dy * dy).isqrt() + 1
+}
+
+fn neighboring_section_indices(
+    params: &SkyParams,
+    section_idx: usize,
+) -> impl Iterator<Item = usize> {
+    let width_sections = params.width_sections;
+    let height_sections = params.height_sections;
+    let section_x = section_idx % width_sections;
+    let section_y = section_idx / width_sections;
 
     (-1..=1).flat_map(move |y_off| {
         (-1..=1).filter_map(move |x_off| {
@@ -56,54 +195,55 @@ fn neighboring_section_indices(section_idx: usize) -> impl Iterator<Item = usize
             let neighbor_y = section_y as i16 + y_off;
 
             if neighbor_x < 0
-                || neighbor_x >= SKY_WIDTH_SECTIONS as i16
+                || neighbor_x >= width_sections as i16
                 || neighbor_y < 0
-                || neighbor_y >= SKY_HEIGHT_SECTIONS as i16
+                || neighbor_y >= height_sections as i16
             {
                 return None;
             }
 
-            Some((neighbor_y as usize * SKY_WIDTH_SECTIONS) + neighbor_x as usize)
+            Some((neighbor_y as usize * width_sections) + neighbor_x as usize)
         })
     })
 }
 
-fn check_distances(sections: &Vec<Vec<u16>>, stars: &[Star], x: i16, y: i16) -> Option<(i16, i16)> {
-    let section_x = (x as usize / SECTION_WIDTH) as i16;
-    let section_y = (y as usize / SECTION_HEIGHT) as i16;
-    let section = (section_y as usize * SKY_WIDTH_SECTIONS) + section_x as usize;
+fn check_distances(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
+    stars: &[Star],
+    x: i16,
+    y: i16,
+) -> Option<(i16, i16)> {
+    let section = params.section_idx(x, y)?;
+    let dist_min = params.star_dist_min as i32;
+    let dist_max_for_line = params.star_dist_max_for_line as i32;
 
     let mut closest_x = 0;
     let mut closest_y = 0;
     let mut closest_dist = i32::MAX;
 
-    for idx in neighboring_section_indices(section) {
+    for idx in neighboring_section_indices(params, section) {
         for star_node_idx in &sections[idx] {
             let star = &stars[*star_node_idx as usize];
             let dx = (x - star.x) as i32;
             let dy = (y - star.y) as i32;
-            // Add 1 here, to avoid allowing too large distances,
-            // because isqrt rounds down.
-            let dist = (dx * dx + dy * dy).isqrt() + 1;
+            let dist = star_distance(dx, dy);
 
             if dist == 0 {
                 // Star is identical to another star, use arbitrary adjustment.
                 return Some((10, 10));
             }
 
-            if dist < STAR_DIST_MIN as i32 {
+            if dist < dist_min {
                 // Star is too close to another star
-                return Some((
-                    (dx * STAR_DIST_MIN as i32 / dist) as i16,
-                    (dy * STAR_DIST_MIN as i32 / dist) as i16,
-                ));
+                return Some(((dx * dist_min / dist) as i16, (dy * dist_min / dist) as i16));
             }
 
-            if dist > STAR_DIST_MAX_FOR_LINE as i32 && dist <= STAR_DIST_DEAD_ZONE_END as i32 {
+            if dist > dist_max_for_line && dist <= params.star_dist_dead_zone_end as i32 {
                 // Star is within dead zone of another star
                 return Some((
-                    (dx * STAR_DIST_MAX_FOR_LINE as i32 / dist) as i16,
-                    (dy * STAR_DIST_MAX_FOR_LINE as i32 / dist) as i16,
+                    (dx * dist_max_for_line / dist) as i16,
+                    (dy * dist_max_for_line / dist) as i16,
                 ));
             }
 
@@ -115,42 +255,25 @@ fn check_distances(sections: &Vec<Vec<u16>>, stars: &[Star], x: i16, y: i16) ->
         }
     }
 
-    if closest_dist > STAR_DIST_MAX_FOR_LINE as i32 {
+    if closest_dist > dist_max_for_line {
         let dx = (closest_x - x) as i32;
         let dy = (closest_y - y) as i32;
         return Some((
-            (dx * STAR_DIST_MAX_FOR_LINE as i32 / closest_dist) as i16,
-            (dy * STAR_DIST_MAX_FOR_LINE as i32 / closest_dist) as i16,
+            (dx * dist_max_for_line / closest_dist) as i16,
+            (dy * dist_max_for_line / closest_dist) as i16,
         ));
     }
     None
 }
 
-fn is_in_bounds(x: i16, y: i16) -> bool {
-    x >= 0
-        && x < (SKY_WIDTH_SECTIONS * SECTION_WIDTH) as i16
-        && y >= 0
-        && y < (SKY_HEIGHT_SECTIONS * SECTION_HEIGHT) as i16
-}
-
 fn add_star(
+    params: &SkyParams,
     star: Star,
-    sections: &mut Vec<Vec<u16>>,
+    sections: &mut [Vec<u16>],
     filled_section_indices: &mut Vec<usize>,
     stars: &mut Vec<Star>,
 ) -> Option<(usize, usize)> {
-    let section_x = (star.x as usize / SECTION_WIDTH) as i16;
-    let section_y = (star.y as usize / SECTION_HEIGHT) as i16;
-
-    if section_x < 0
-        || section_x >= SKY_WIDTH_SECTIONS as i16
-        || section_y < 0
-        || section_y >= SKY_HEIGHT_SECTIONS as i16
-    {
-        return None; // Out of bounds
-    }
-
-    let section_idx = (section_y as usize * SKY_WIDTH_SECTIONS) + section_x as usize;
+    let section_idx = params.section_idx(star.x, star.y)?;
     sections[section_idx].push(stars.len() as u16);
     stars.push(star);
 
@@ -161,8 +284,9 @@ fn add_star(
     Some((section_idx, sections[section_idx].len() - 1))
 }
 
-fn get_stars_within_range(
-    sections: &Vec<Vec<u16>>,
+pub(crate) fn get_stars_within_range(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
     stars: &[Star],
     base_section_idx: usize,
     base_idx: usize,
@@ -171,7 +295,7 @@ fn get_stars_within_range(
     let mut out = Vec::new();
     let base_star_idx = sections[base_section_idx][base_idx] as usize;
     let max_dist_sq = (max_distance * max_distance) as i32;
-    for section_idx in neighboring_section_indices(base_section_idx) {
+    for section_idx in neighboring_section_indices(params, base_section_idx) {
         for (idx, star_idx_u16) in sections[section_idx].iter().enumerate() {
             let star_idx = *star_idx_u16 as usize;
             if section_idx == base_section_idx && idx == base_idx {
@@ -194,208 +318,465 @@ fn get_stars_within_range(
     out
 }
 
-pub(crate) fn get_constellation(links: &[Link], start_idx: usize) -> (Vec<u16>, Vec<u16>) {
-    let mut queue = vec![start_idx as u16];
-    let mut seen = vec![];
-    let mut line_indices = vec![];
-
-    while let Some(idx) = queue.pop() {
-        seen.push(idx);
-        for (line_idx, line) in links.iter().enumerate() {
-            let next = if line.start_idx == idx {
-                line.end_idx
-            } else if line.end_idx == idx {
-                line.start_idx
-            } else {
-                continue;
-            };
-
-            line_indices.push(line_idx as u16);
-            if !seen.contains(&next) {
-                queue.push(next)
-            }
-        }
-    }
-
-    (seen, line_indices)
-}
-
+/// Randomly pick a preset line for a newly added star.
 fn handle_preset_line(
+    params: &SkyParams,
     stars: &[Star],
-    links: &mut Vec<Link>,
-    sections: &Vec<Vec<u16>>,
+    constellations: &Constellations,
+    sections: &[Vec<u16>],
     new_star_section_idx: usize,
     new_star_idx: usize,
     rng: &mut u32,
-) {
+) -> Option<Link> {
     let close_stars = get_stars_within_range(
+        params,
         sections,
         stars,
         new_star_section_idx,
         new_star_idx,
-        STAR_DIST_MAX_FOR_PRESET_LINE,
+        params.star_dist_max_for_preset_line,
     );
     if close_stars.is_empty() {
-        return;
+        return None;
     }
 
-    if next_random(rng) as f32 / (u32::MAX as f32) < PRESET_LINE_CHANCE {
+    if next_random(rng) as f32 / (u32::MAX as f32) < params.preset_line_chance {
         let close_star_idx = next_random(rng) as usize % close_stars.len();
         let (target_section, target_idx) = close_stars[close_star_idx];
         let target_star_idx = sections[target_section][target_idx] as usize;
 
-        let constellation_size = get_constellation(links, target_star_idx).0.len();
-        if constellation_size >= MAX_PRESET_CONSTELLATION_SIZE {
-            return; // Too many stars already connected
+        if constellations.size(target_star_idx) >= params.max_preset_constellation_size {
+            return None; // Too many stars already connected
         }
 
-        let new_line = Link::new(
+        return Some(Link::new(
             target_star_idx as u16,
-            sections[new_star_section_idx][new_star_idx] as u16,
+            sections[new_star_section_idx][new_star_idx],
             STYLE_BRIGHT,
-        );
-        links.push(new_line);
+        ));
     }
+    None
 }
 
-pub(crate) fn generate_sky(mut seed: u32) -> (Vec<Vec<u16>>, Vec<Star>, Vec<Link>) {
-    let mut sections = vec![Vec::with_capacity(8); SKY_WIDTH_SECTIONS * SKY_HEIGHT_SECTIONS];
+/// Generate the stars and preset lines of a sky. This usually places
+/// `max_stars` stars, but may give up early if the sky is too crowded.
+pub(crate) fn generate_sky(
+    params: &SkyParams,
+    mut seed: u32,
+) -> (Vec<Vec<u16>>, Vec<Star>, Vec<Link>) {
+    // Magnitudes and twinkle phases use their own random sequence,
+    // so that they do not change the layout.
+    let mut magnitude_seed = seed.rotate_left(16);
+    let mut sections = vec![Vec::new(); params.width_sections * params.height_sections];
     let mut stars = vec![];
     let mut links = vec![];
+    let mut constellations = Constellations::new(0, &links);
 
     let mut filled_section_indices = vec![];
 
     add_star(
+        params,
         Star::new(
-            (SKY_WIDTH_SECTIONS / 2 * SECTION_WIDTH) as i16
-                + (next_random(&mut seed) % (SECTION_WIDTH as u32)) as i16,
-            (SKY_HEIGHT_SECTIONS / 2 * SECTION_HEIGHT) as i16
-                + (next_random(&mut seed) % (SECTION_HEIGHT as u32)) as i16,
+            (params.width_sections / 2 * params.section_width) as i16
+                + (next_random(&mut seed) % (params.section_width as u32)) as i16,
+            (params.height_sections / 2 * params.section_height) as i16
+                + (next_random(&mut seed) % (params.section_height as u32)) as i16,
             true,
         ),
         &mut sections,
         &mut filled_section_indices,
         &mut stars,
     );
+    constellations.add_star();
 
-    while stars.len() < MAX_STARS {
+    for _ in 0..params.max_stars * MAX_ATTEMPTS_PER_STAR {
+        if stars.len() >= params.max_stars {
+            break;
+        }
         let section_idx = next_random(&mut seed) as usize % filled_section_indices.len();
-        let section_x = filled_section_indices[section_idx] % SKY_WIDTH_SECTIONS;
-        let section_y = filled_section_indices[section_idx] / SKY_WIDTH_SECTIONS;
-        let mut x = (section_x * SECTION_WIDTH) as i16
-            + (next_random(&mut seed) as usize % SECTION_WIDTH) as i16;
-        let mut y = (section_y * SECTION_HEIGHT) as i16
-            + (next_random(&mut seed) as usize % SECTION_HEIGHT) as i16;
+        let section_x = filled_section_indices[section_idx] % params.width_sections;
+        let section_y = filled_section_indices[section_idx] / params.width_sections;
+        let mut x = (section_x * params.section_width) as i16
+            + (next_random(&mut seed) as usize % params.section_width) as i16;
+        let mut y = (section_y * params.section_height) as i16
+            + (next_random(&mut seed) as usize % params.section_height) as i16;
 
         for _ in 0..MAX_ADJUSTMENTS_PER_STAR {
-            if !is_in_bounds(x, y) {
+            if !params.is_in_bounds(x, y) {
                 break;
-            } else if let Some((dx, dy)) = check_distances(&sections, &stars, x, y) {
+            } else if let Some((dx, dy)) = check_distances(params, &sections, &stars, x, y) {
                 x += dx + ((next_random(&mut seed) & 0x7) as i16 - 3);
                 y += dy + ((next_random(&mut seed) & 0x7) as i16 - 3);
             } else if let Some((new_star_section_idx, new_star_idx)) = add_star(
+                params,
                 Star::new(x, y, true),
                 &mut sections,
                 &mut filled_section_indices,
                 &mut stars,
             ) {
-                handle_preset_line(
+                constellations.add_star();
+                if let Some(link) = handle_preset_line(
+                    params,
                     &stars,
-                    &mut links,
+                    &constellations,
                     &sections,
                     new_star_section_idx,
                     new_star_idx,
                     &mut seed,
-                );
+                ) {
+                    constellations.add_link(links.len(), &link);
+                    links.push(link);
+                }
                 break;
             }
         }
     }
 
+    for star in stars.iter_mut() {
+        star.magnitude = Magnitude::from_random(next_random(&mut magnitude_seed));
+    }
+    for star in stars.iter_mut() {
+        star.twinkle_phase = next_random(&mut magnitude_seed) as u8;
+    }
+
     (sections, stars, links)
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum StarState {
+    Unknown,
+    Visited,
+    /// The star is part of a cluster that is large enough for a constellation.
+    Ok,
+}
+
+/// Collect the bright stars that can be reached from the given star by
+/// following possible lines. The search stops early once the cluster is known
+/// to be large enough for a constellation, in which case `true` is returned and
+/// all collected stars are marked as `Ok`.
 fn collect_reachable_stars(
-    sections: &Vec<Vec<u16>>,
+    params: &SkyParams,
+    sections: &[Vec<u16>],
     stars: &[Star],
     base_section_idx: usize,
     base_star_idx: usize,
-) -> Vec<(usize, usize)> {
+    state: &mut [StarState],
+) -> (Vec<(usize, usize)>, bool) {
     let mut current_cluster = Vec::new();
     let mut open_start = 0;
+    let mut is_ok = false;
 
     current_cluster.push((base_section_idx, base_star_idx));
+    state[sections[base_section_idx][base_star_idx] as usize] = StarState::Visited;
 
-    while open_start < current_cluster.len() {
+    'search: while open_start < current_cluster.len() {
         let (section_idx, star_idx) = current_cluster[open_start];
         open_start += 1;
 
         let reachable_stars = get_stars_within_range(
+            params,
             sections,
             stars,
             section_idx,
             star_idx,
-            STAR_DIST_MAX_FOR_LINE,
+            params.star_dist_max_for_line,
         );
-        for star in reachable_stars {
-            if !current_cluster.contains(&star) {
-                current_cluster.push(star);
+        for (section_idx, star_idx) in reachable_stars {
+            let star_state = &mut state[sections[section_idx][star_idx] as usize];
+            match star_state {
+                StarState::Ok => {
+                    is_ok = true;
+                    break 'search;
+                }
+                StarState::Visited => {}
+                StarState::Unknown => {
+                    *star_state = StarState::Visited;
+                    current_cluster.push((section_idx, star_idx));
+                    if current_cluster.len() >= params.constellation_threshold {
+                        is_ok = true;
+                        break 'search;
+                    }
+                }
             }
         }
     }
-    current_cluster
+
+    if is_ok {
+        for (section_idx, star_idx) in &current_cluster {
+            state[sections[*section_idx][*star_idx] as usize] = StarState::Ok;
+        }
+    }
+    (current_cluster, is_ok)
 }
 
-pub(crate) fn dim_lonely_stars(sections: &Vec<Vec<u16>>, stars: &mut [Star], links: &mut [Link]) {
-    #[derive(Clone, Copy)]
-    enum StarState {
-        Unknown,
-        Ok,
-        Unreachable,
+/// Dim the bright stars in clusters which are too small to ever form a
+/// constellation, starting the search from `seeds`. Returns the indices
+/// of the stars and links that were dimmed.
+fn dim_lonely_clusters(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
+    stars: &mut [Star],
+    links: &mut [Link],
+    seeds: impl IntoIterator<Item = (usize, usize)>,
+) -> (Vec<u16>, Vec<u16>) {
+    let mut state = vec![StarState::Unknown; stars.len()];
+    let mut dimmed_stars = Vec::new();
+
+    for (section_idx, star_idx) in seeds {
+        let idx = sections[section_idx][star_idx] as usize;
+        if state[idx] != StarState::Unknown || !stars[idx].bright {
+            continue;
+        }
+
+        let (cluster, is_ok) =
+            collect_reachable_stars(params, sections, stars, section_idx, star_idx, &mut state);
+        if !is_ok {
+            for (section_idx, star_idx) in cluster {
+                let idx = sections[section_idx][star_idx];
+                stars[idx as usize].bright = false;
+                dimmed_stars.push(idx);
+            }
+        }
     }
-    let mut state = Vec::with_capacity(stars.len());
 
-    for star in stars.iter() {
-        if !star.bright {
-            state.push(StarState::Ok);
-        } else {
-            state.push(StarState::Unknown);
+    let mut dimmed_links = Vec::new();
+    if !dimmed_stars.is_empty() {
+        for (idx, link) in links.iter_mut().enumerate() {
+            if link.style == STYLE_BRIGHT
+                && (!stars[link.start_idx as usize].bright || !stars[link.end_idx as usize].bright)
+            {
+                link.style = STYLE_DIM;
+                dimmed_links.push(idx as u16);
+            }
         }
     }
+    (dimmed_stars, dimmed_links)
+}
+
+/// Dim all bright stars which cannot be part of a constellation.
+pub(crate) fn dim_lonely_stars(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
+    stars: &mut [Star],
+    links: &mut [Link],
+) -> (Vec<u16>, Vec<u16>) {
+    let seeds = sections
+        .iter()
+        .enumerate()
+        .flat_map(|(section_idx, section)| (0..section.len()).map(move |idx| (section_idx, idx)));
+    dim_lonely_clusters(params, sections, stars, links, seeds)
+}
+
+/// Dim the bright stars which cannot be part of a constellation anymore,
+/// after the stars in `dimmed` were dimmed. Only the clusters next to these
+/// stars are checked, so every other bright star must already be part of a
+/// large enough cluster, e.g. through an earlier call to `dim_lonely_stars`.
+pub(crate) fn dim_lonely_stars_near(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
+    stars: &mut [Star],
+    links: &mut [Link],
+    dimmed: &[u16],
+) -> (Vec<u16>, Vec<u16>) {
+    let mut seeds = Vec::new();
+    for star_idx in dimmed {
+        let star = &stars[*star_idx as usize];
+        let Some(section_idx) = params.section_idx(star.x, star.y) else {
+            continue;
+        };
+        let Some(idx) = sections[section_idx].iter().position(|idx| idx == star_idx) else {
+            continue;
+        };
+        seeds.extend(get_stars_within_range(
+            params,
+            sections,
+            stars,
+            section_idx,
+            idx,
+            params.star_dist_max_for_line,
+        ));
+    }
+    dim_lonely_clusters(params, sections, stars, links, seeds)
+}
 
-    // Mark stars which can still be part of a constellation
-    for base_section_idx in 0..sections.len() {
-        for base_star_idx in 0..sections[base_section_idx].len() {
-            if let StarState::Unknown = state[sections[base_section_idx][base_star_idx] as usize] {
-                let cluster =
-                    collect_reachable_stars(sections, stars, base_section_idx, base_star_idx);
-
-                let new_state = if cluster.len() >= CONSTELLATION_THRESHOLD {
-                    StarState::Ok
-                } else {
-                    StarState::Unreachable
-                };
-
-                for star in cluster {
-                    let (section_idx, star_idx) = star;
-                    state[sections[section_idx][star_idx] as usize] = new_state;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Dim a cluster of up to `constellation_threshold` bright stars around
+    /// some bright star, like completing a constellation would.
+    fn dim_some_constellation(
+        params: &SkyParams,
+        sections: &[Vec<u16>],
+        stars: &mut [Star],
+        rng: &mut u32,
+    ) -> Option<Vec<u16>> {
+        let bright: Vec<(usize, usize)> = sections
+            .iter()
+            .enumerate()
+            .flat_map(|(section_idx, section)| {
+                section
+                    .iter()
+                    .enumerate()
+                    .map(move |(idx, star_idx)| (section_idx, idx, *star_idx))
+            })
+            .filter(|(_, _, star_idx)| stars[*star_idx as usize].bright)
+            .map(|(section_idx, idx, _)| (section_idx, idx))
+            .collect();
+        if bright.is_empty() {
+            return None;
+        }
+
+        // Grow a connected group of bright stars, as the player would.
+        let (section_idx, idx) = bright[next_random(rng) as usize % bright.len()];
+        let mut group = vec![(section_idx, idx)];
+        let mut open_start = 0;
+        while open_start < group.len() && group.len() < params.constellation_threshold {
+            let (section_idx, idx) = group[open_start];
+            open_start += 1;
+            let max_dist = params.star_dist_max_for_line;
+            for star in get_stars_within_range(params, sections, stars, section_idx, idx, max_dist)
+            {
+                if !group.contains(&star) && group.len() < params.constellation_threshold {
+                    group.push(star);
                 }
             }
         }
+        let dimmed: Vec<u16> = group
+            .iter()
+            .map(|(section_idx, idx)| sections[*section_idx][*idx])
+            .collect();
+        for star_idx in &dimmed {
+            stars[*star_idx as usize].bright = false;
+        }
+        Some(dimmed)
     }
 
-    // Dim lonely stars
-    for (idx, state) in state.iter().enumerate() {
-        if let StarState::Unreachable = state {
-            stars[idx].bright = false;
+    #[test]
+    fn brighter_stars_are_rarer() {
+        let (_, stars, _) = generate_sky(&SkyParams::HUGE, 123456);
+        let count = |magnitude| {
+            stars
+                .iter()
+                .filter(|star| star.magnitude == magnitude)
+                .count()
+        };
+        let (faint, medium, brilliant) = (
+            count(Magnitude::Faint),
+            count(Magnitude::Medium),
+            count(Magnitude::Brilliant),
+        );
+        assert!(brilliant > 0);
+        assert!(
+            medium > 2 * brilliant,
+            "{medium} medium, {brilliant} brilliant"
+        );
+        assert!(faint > 2 * medium, "{faint} faint, {medium} medium");
+    }
+
+    #[test]
+    fn params_fit_into_saves_and_sky() {
+        for params in [
+            SkyParams::STANDARD,
+            SkyParams::TUTORIAL,
+            SkyParams::DENSE,
+            SkyParams::HUGE,
+        ] {
+            assert!(params.is_valid());
         }
+        let too_wide = SkyParams {
+            section_width: 256,
+            ..SkyParams::STANDARD
+        };
+        assert!(!too_wide.is_valid());
+        let too_crowded = SkyParams {
+            max_stars: 2000,
+            ..SkyParams::STANDARD
+        };
+        assert!(!too_crowded.is_valid());
     }
 
-    for link in links.iter_mut() {
-        if link.style == STYLE_BRIGHT
-            && (!stars[link.start_idx as usize].bright || !stars[link.end_idx as usize].bright)
-        {
-            link.style = STYLE_DIM;
+    #[test]
+    fn crowded_sky_gives_up() {
+        let params = SkyParams {
+            max_stars: 163,
+            ..SkyParams::TUTORIAL
+        };
+        assert!(params.is_valid());
+        let (_, stars, _) = generate_sky(&params, 123456);
+        assert!(stars.len() < params.max_stars);
+    }
+
+    #[test]
+    fn incremental_dimming_matches_full_dimming() {
+        let params = SkyParams::STANDARD;
+        for seed in 1..=20 {
+            let (sections, mut stars, mut links) = generate_sky(&params, seed);
+            dim_lonely_stars(&params, &sections, &mut stars, &mut links);
+
+            let mut rng = seed;
+            while let Some(dimmed) =
+                dim_some_constellation(&params, &sections, &mut stars, &mut rng)
+            {
+                let (mut full_stars, mut full_links) = (stars.clone(), links.clone());
+                dim_lonely_stars(&params, &sections, &mut full_stars, &mut full_links);
+                dim_lonely_stars_near(&params, &sections, &mut stars, &mut links, &dimmed);
+
+                assert!(
+                    stars
+                        .iter()
+                        .zip(&full_stars)
+                        .all(|(a, b)| a.bright == b.bright),
+                    "Seed {seed}"
+                );
+                assert!(
+                    links
+                        .iter()
+                        .zip(&full_links)
+                        .all(|(a, b)| a.style == b.style),
+                    "Seed {seed}"
+                );
+            }
+        }
+    }
+
+    /// Compare the cost of dimming lonely stars after each completed constellation,
+    /// run with `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_dim_lonely_stars() {
+        let huge = SkyParams {
+            max_stars: 3500,
+            ..SkyParams::HUGE
+        };
+        for params in [SkyParams::STANDARD, huge] {
+            let (sections, mut stars, mut links) = generate_sky(&params, 123456);
+            dim_lonely_stars(&params, &sections, &mut stars, &mut links);
+            let star_count = stars.len();
+
+            let mut rng = 1;
+            let mut completions = 0;
+            let (mut full_time, mut incremental_time) = (Duration::ZERO, Duration::ZERO);
+            while let Some(dimmed) =
+                dim_some_constellation(&params, &sections, &mut stars, &mut rng)
+            {
+                let (mut full_stars, mut full_links) = (stars.clone(), links.clone());
+                let start = Instant::now();
+                dim_lonely_stars(&params, &sections, &mut full_stars, &mut full_links);
+                full_time += start.elapsed();
+
+                let start = Instant::now();
+                dim_lonely_stars_near(&params, &sections, &mut stars, &mut links, &dimmed);
+                incremental_time += start.elapsed();
+                completions += 1;
+            }
+
+            println!(
+                "{star_count} stars, {completions} constellations: full {:?}, incremental {:?} per constellation",
+                full_time / completions,
+                incremental_time / completions
+            );
         }
     }
 }
@@ -0,0 +1,187 @@
+use crate::constellations::Constellations;
+use crate::line::Link;
+use crate::star::Star;
+use crate::util::{SkyParams, star_distance};
+
+/// A property of a generated sky that does not hold.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SkyViolation {
+    /// Two stars are closer than `star_dist_min`.
+    TooClose { a: u16, b: u16, dist: i32 },
+    /// The distance between two stars is within the dead zone
+    /// `star_dist_max_for_line < d <= star_dist_dead_zone_end`.
+    InDeadZone { a: u16, b: u16, dist: i32 },
+    /// A preset constellation contains more than `max_preset_constellation_size` stars.
+    PresetConstellationTooLarge { star_idx: u16, size: usize },
+    /// A star lies outside of the sky.
+    OutOfBounds { star_idx: u16 },
+    /// A star is not listed in the section that contains its coordinates.
+    MissingFromSection { star_idx: u16, section_idx: usize },
+    /// A section lists a star that does not belong to it.
+    WrongSection { star_idx: u16, section_idx: usize },
+}
+
+/// Check a sky created by `generate_sky` against the rules that the rest of
+/// the game relies on, and return every violation that was found.
+pub(crate) fn validate_sky(
+    params: &SkyParams,
+    sections: &[Vec<u16>],
+    stars: &[Star],
+    links: &[Link],
+) -> Vec<SkyViolation> {
+    let mut out = Vec::new();
+
+    for (a, star_a) in stars.iter().enumerate() {
+        for (b, star_b) in stars.iter().enumerate().skip(a + 1) {
+            let dist = star_distance((star_a.x - star_b.x) as i32, (star_a.y - star_b.y) as i32);
+            let (a, b) = (a as u16, b as u16);
+            if dist < params.star_dist_min as i32 {
+                out.push(SkyViolation::TooClose { a, b, dist });
+            } else if dist > params.star_dist_max_for_line as i32
+                && dist <= params.star_dist_dead_zone_end as i32
+            {
+                out.push(SkyViolation::InDeadZone { a, b, dist });
+            }
+        }
+    }
+
+    let constellations = Constellations::new(stars.len(), links);
+    let mut checked = vec![false; stars.len()];
+    for link in links {
+        if checked[link.start_idx as usize] {
+            continue;
+        }
+
+        let constellation = constellations.stars(link.start_idx as usize);
+        for star_idx in constellation {
+            checked[*star_idx as usize] = true;
+        }
+        if constellation.len() > params.max_preset_constellation_size {
+            out.push(SkyViolation::PresetConstellationTooLarge {
+                star_idx: link.start_idx,
+                size: constellation.len(),
+            });
+        }
+    }
+
+    for (star_idx, star) in stars.iter().enumerate() {
+        let star_idx = star_idx as u16;
+        let Some(section_idx) = params.section_idx(star.x, star.y) else {
+            out.push(SkyViolation::OutOfBounds { star_idx });
+            continue;
+        };
+
+        if !sections[section_idx].contains(&star_idx) {
+            out.push(SkyViolation::MissingFromSection {
+                star_idx,
+                section_idx,
+            });
+        }
+    }
+
+    for (section_idx, section) in sections.iter().enumerate() {
+        for star_idx in section {
+            let star = &stars[*star_idx as usize];
+            if params.section_idx(star.x, star.y) != Some(section_idx) {
+                out.push(SkyViolation::WrongSection {
+                    star_idx: *star_idx,
+                    section_idx,
+                });
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line::STYLE_BRIGHT;
+    use crate::util::{generate_sky, next_random};
+
+    fn check_seeds(params: &SkyParams, count: usize) {
+        assert!(params.is_valid());
+        let mut rng = 0x5eed;
+        for _ in 0..count {
+            let seed = next_random(&mut rng);
+            let (sections, stars, links) = generate_sky(params, seed);
+            assert_eq!(stars.len(), params.max_stars);
+            let violations = validate_sky(params, &sections, &stars, &links);
+            assert!(violations.is_empty(), "Seed {seed}: {violations:?}");
+        }
+    }
+
+    #[test]
+    fn generated_skies_are_valid() {
+        check_seeds(&SkyParams::STANDARD, 2000);
+    }
+
+    #[test]
+    fn generated_skies_are_valid_for_all_presets() {
+        check_seeds(&SkyParams::TUTORIAL, 1000);
+        check_seeds(&SkyParams::DENSE, 200);
+        check_seeds(&SkyParams::HUGE, 5);
+    }
+
+    #[test]
+    fn reports_violations() {
+        let params = SkyParams::STANDARD;
+        let (mut sections, mut stars, mut links) = generate_sky(&params, 123456);
+
+        // Move a star directly next to another one.
+        stars[1].x = stars[0].x + 1;
+        stars[1].y = stars[0].y;
+        // Add a star without registering it in the sections.
+        stars.push(Star::new(5, 5, true));
+        // Add a star in the dead zone around the first star.
+        let dead_zone_dx = (1..)
+            .find(|dx| star_distance(*dx, 0) > params.star_dist_max_for_line as i32)
+            .unwrap();
+        let dead_zone_idx = stars.len() as u16;
+        let (x, y) = (stars[0].x, stars[0].y);
+        let dead_zone_x = if x >= dead_zone_dx as i16 {
+            x - dead_zone_dx as i16
+        } else {
+            x + dead_zone_dx as i16
+        };
+        stars.push(Star::new(dead_zone_x, y, true));
+        // Add a star outside of the sky.
+        let out_of_bounds_idx = stars.len() as u16;
+        stars.push(Star::new(-5, params.height() as i16 + 5, true));
+        // Chain up more stars than a preset constellation may contain.
+        for idx in 2..=params.max_preset_constellation_size as u16 + 1 {
+            links.push(Link::new(idx, idx + 1, STYLE_BRIGHT));
+        }
+        sections[0].push(0);
+
+        let violations = validate_sky(&params, &sections, &stars, &links);
+        assert!(violations.contains(&SkyViolation::TooClose {
+            a: 0,
+            b: 1,
+            dist: 2
+        }));
+        assert!(
+            violations
+                .iter()
+                .any(|v| matches!(v, SkyViolation::PresetConstellationTooLarge { .. }))
+        );
+        assert!(
+            violations
+                .iter()
+                .any(|v| matches!(v, SkyViolation::MissingFromSection { section_idx: 0, .. }))
+        );
+        assert!(violations.contains(&SkyViolation::WrongSection {
+            star_idx: 0,
+            section_idx: 0
+        }));
+        assert!(violations.contains(&SkyViolation::InDeadZone {
+            a: 0,
+            b: dead_zone_idx,
+            dist: star_distance(dead_zone_dx, 0)
+        }));
+        assert!(violations.contains(&SkyViolation::OutOfBounds {
+            star_idx: out_of_bounds_idx
+        }));
+    }
+}